@@ -0,0 +1,361 @@
+//! Whole-game gas benchmark: every ROM in a directory for a fixed number of ticks with scripted
+//! input, summarised per game into a snapshot file that later runs are checked against.
+//!
+//! The snapshot follows `forge snapshot`, one line per ROM:
+//!
+//! ```text
+//! PONG (ticks: 200, total: 13400000, mean: 67000, p99: 344000)
+//! ```
+
+use std::collections::BTreeMap;
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use alloy::network::{Network, ReceiptResponse};
+use alloy::providers::Provider;
+use alloy::transports::Transport;
+use desktop::client;
+use desktop::input::Script;
+
+use crate::rom::Rom;
+use crate::Solchip8::{self, Solchip8Instance};
+
+/// Gas used by the `tick()` transactions of one run.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct GasStats {
+    pub ticks: u64,
+    pub total: u64,
+    pub mean: u64,
+    pub p99: u64,
+}
+
+impl GasStats {
+    pub fn new(samples: &[u64]) -> Self {
+        let mut sorted = samples.to_vec();
+        sorted.sort_unstable();
+        let ticks = sorted.len() as u64;
+        let total = sorted.iter().sum();
+        let p99 = match sorted.len() {
+            0 => 0,
+            len => sorted[(len * 99).div_ceil(100) - 1],
+        };
+        GasStats {
+            ticks,
+            total,
+            mean: total.checked_div(ticks).unwrap_or(0),
+            p99,
+        }
+    }
+
+    fn parse(s: &str) -> Option<Self> {
+        let mut stats = GasStats::default();
+        for field in s.split(", ") {
+            let (key, value) = field.split_once(": ")?;
+            let value = value.parse().ok()?;
+            match key {
+                "ticks" => stats.ticks = value,
+                "total" => stats.total = value,
+                "mean" => stats.mean = value,
+                "p99" => stats.p99 = value,
+                _ => return None,
+            }
+        }
+        Some(stats)
+    }
+}
+
+impl fmt::Display for GasStats {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "ticks: {}, total: {}, mean: {}, p99: {}",
+            self.ticks, self.total, self.mean, self.p99
+        )
+    }
+}
+
+/// Result of running one ROM.
+#[derive(Debug, Clone)]
+pub struct RomRun {
+    /// Gas of every `tick()` that went through.
+    pub gas: Vec<u64>,
+    /// Why the run stopped early.
+    pub revert: Option<String>,
+}
+
+/// Run the program loaded in `emu` for `ticks` ticks, sending `input` before each tick.
+pub async fn run_rom<T, P, N>(
+    emu: &Solchip8Instance<T, P, N>,
+    ticks: u64,
+    input: &Script,
+) -> Result<RomRun, alloy::contract::Error>
+where
+    T: Transport + Clone,
+    P: Provider<T, N>,
+    N: Network,
+{
+    let mut gas = Vec::with_capacity(ticks as usize);
+    for tick in 0..ticks {
+        input.apply(tick, emu).await?;
+        match client::transact(emu.tick()).await {
+            Ok(receipt) => gas.push(receipt.gas_used() as u64),
+            Err(e) => {
+                return Ok(RomRun {
+                    gas,
+                    revert: Some(client::revert_reason(&e)),
+                })
+            }
+        }
+    }
+    Ok(RomRun { gas, revert: None })
+}
+
+/// Every regular file in `dir`, by name.
+pub fn rom_files(dir: &Path) -> io::Result<Vec<PathBuf>> {
+    let mut files = Vec::new();
+    for entry in fs::read_dir(dir)? {
+        let path = entry?.path();
+        if path.is_file() {
+            files.push(path);
+        }
+    }
+    files.sort();
+    Ok(files)
+}
+
+/// What one ROM did: the gas of its ticks and the revert that ended the run early.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Entry {
+    pub stats: GasStats,
+    pub revert: Option<String>,
+}
+
+impl Entry {
+    /// Parse a snapshot line, `NAME (stats)` with ` reverted: reason` after it if it reverted.
+    fn parse(line: &str) -> Option<(String, Self)> {
+        let (name, rest) = line.split_once(" (")?;
+        let (stats, rest) = rest.split_once(')')?;
+        let revert = match rest {
+            "" => None,
+            rest => Some(rest.strip_prefix(" reverted: ")?.to_string()),
+        };
+        let stats = GasStats::parse(stats)?;
+        Some((name.to_string(), Entry { stats, revert }))
+    }
+
+    fn line(&self, name: &str) -> String {
+        match &self.revert {
+            Some(reason) => format!("{name} ({}) reverted: {reason}", self.stats),
+            None => format!("{name} ({})", self.stats),
+        }
+    }
+
+    /// How `self` changed since `before`, and whether that passes: mean and p99 gas may move
+    /// by up to `tolerance` percent, the tick count and the revert must stay the same.
+    fn compare(&self, before: &Entry, tolerance: f64) -> (bool, String) {
+        let change =
+            |before: u64, now: u64| 100.0 * (now as f64 - before as f64) / before.max(1) as f64;
+        let mean = change(before.stats.mean, self.stats.mean);
+        let p99 = change(before.stats.p99, self.stats.p99);
+        let mut report = format!(
+            "mean {} -> {} ({mean:+.2}%), p99 {} -> {} ({p99:+.2}%)",
+            before.stats.mean, self.stats.mean, before.stats.p99, self.stats.p99
+        );
+        let mut ok = mean <= tolerance && p99 <= tolerance;
+        if self.stats.ticks != before.stats.ticks {
+            ok = false;
+            report += &format!(", ticks {} -> {}", before.stats.ticks, self.stats.ticks);
+        }
+        if self.revert != before.revert {
+            ok = false;
+            report += &match (&before.revert, &self.revert) {
+                (None, Some(reason)) => format!(", now reverts: {reason}"),
+                (Some(_), None) => ", no longer reverts".to_string(),
+                (Some(was), Some(reason)) => {
+                    format!(", reverts with `{reason}` instead of `{was}`")
+                }
+                (None, None) => unreachable!(),
+            };
+        }
+        let verdict = if !ok {
+            "REGRESSED"
+        } else if mean < -tolerance || p99 < -tolerance {
+            "improved"
+        } else {
+            "ok"
+        };
+        (ok, format!("{report} {verdict}"))
+    }
+}
+
+fn read_snapshot(path: &Path) -> io::Result<BTreeMap<String, Entry>> {
+    let mut snapshot = BTreeMap::new();
+    for line in fs::read_to_string(path)?.lines() {
+        match Entry::parse(line) {
+            Some((name, entry)) => snapshot.insert(name, entry),
+            None => {
+                let msg = format!("invalid snapshot line `{line}`");
+                return Err(io::Error::new(io::ErrorKind::InvalidData, msg));
+            }
+        };
+    }
+    Ok(snapshot)
+}
+
+fn write_snapshot(path: &Path, results: &BTreeMap<String, Entry>) -> io::Result<()> {
+    let lines: String = results
+        .iter()
+        .map(|(name, entry)| entry.line(name) + "\n")
+        .collect();
+    fs::write(path, lines)
+}
+
+/// Benchmark every ROM in `dir` and write `snapshot`, or with `check` compare against it.
+///
+/// Returns false when a ROM could not be benchmarked, or with `check` when a game's mean or
+/// p99 gas per tick grew by more than `tolerance` percent, it ran a different number of ticks,
+/// it reverts differently, or the games differ from the snapshot's.
+pub async fn run<T, P, N>(
+    provider: P,
+    dir: &Path,
+    ticks: u64,
+    input: Option<Script>,
+    snapshot: &Path,
+    check: bool,
+    tolerance: f64,
+) -> io::Result<bool>
+where
+    T: Transport + Clone,
+    P: Provider<T, N> + Clone,
+    N: Network,
+{
+    let input = input.unwrap_or_else(|| Script::cycle_keys(ticks));
+    let mut results = BTreeMap::new();
+    let mut ok = true;
+    for path in rom_files(dir)? {
+        let name = path.file_name().unwrap().to_string_lossy().to_string();
+        let rom = match Rom::from_file(&path) {
+            Ok(rom) => rom,
+            Err(e) => {
+                println!("{name}: skipped, {e}");
+                ok = false;
+                continue;
+            }
+        };
+        let emu = Solchip8::deploy(provider.clone())
+            .await
+            .map_err(|e| io::Error::other(format!("deploy failed: {e}")))?;
+        if let Err(e) = rom.load(&emu).await {
+            println!("{name}: skipped, ROM load failed: {e}");
+            ok = false;
+            continue;
+        }
+        let run = run_rom(&emu, ticks, &input)
+            .await
+            .map_err(|e| io::Error::other(format!("{name}: {e}")))?;
+        let entry = Entry {
+            stats: GasStats::new(&run.gas),
+            revert: run.revert,
+        };
+        println!("{}", entry.line(&name));
+        results.insert(name, entry);
+    }
+
+    if !check {
+        write_snapshot(snapshot, &results)?;
+        println!("wrote {}", snapshot.display());
+        return Ok(ok);
+    }
+
+    let expected = read_snapshot(snapshot)?;
+    for (name, entry) in &results {
+        let Some(before) = expected.get(name) else {
+            println!("{name}: not in snapshot");
+            ok = false;
+            continue;
+        };
+        let (passed, report) = entry.compare(before, tolerance);
+        ok &= passed;
+        println!("{name}: {report}");
+    }
+    for name in expected.keys().filter(|name| !results.contains_key(*name)) {
+        println!("{name}: in snapshot but not benchmarked");
+        ok = false;
+    }
+    Ok(ok)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entry(ticks: u64, mean: u64, p99: u64, revert: Option<&str>) -> Entry {
+        Entry {
+            stats: GasStats {
+                ticks,
+                total: ticks * mean,
+                mean,
+                p99,
+            },
+            revert: revert.map(str::to_string),
+        }
+    }
+
+    #[test]
+    fn snapshot_lines_round_trip() {
+        for (line, expected) in [
+            (
+                "PONG (ticks: 200, total: 13400000, mean: 67000, p99: 344000)",
+                entry(200, 67000, 344000, None),
+            ),
+            (
+                "BAD (ticks: 3, total: 150000, mean: 50000, p99: 60000) reverted: Stack overflow (depth 16)",
+                entry(3, 50000, 60000, Some("Stack overflow (depth 16)")),
+            ),
+        ] {
+            let (name, parsed) = Entry::parse(line).unwrap();
+            assert_eq!(parsed, expected);
+            assert_eq!(parsed.line(&name), line);
+        }
+        for line in [
+            "PONG",
+            "PONG (ticks: 200, total: 1, mean: 1, p99: 1",
+            "PONG (ticks: 200, total: 1, mean: 1, p99: 1) junk",
+            "PONG (ticks: 200, gas: 1)",
+        ] {
+            assert_eq!(Entry::parse(line), None, "{line}");
+        }
+    }
+
+    #[test]
+    fn gas_within_tolerance_passes() {
+        let before = entry(200, 1000, 2000, None);
+        assert!(entry(200, 1010, 2000, None).compare(&before, 2.0).0);
+        let (ok, report) = entry(200, 900, 1800, None).compare(&before, 2.0);
+        assert!(ok);
+        assert!(report.ends_with("improved"), "{report}");
+        let (ok, report) = entry(200, 1000, 2100, None).compare(&before, 2.0);
+        assert!(!ok);
+        assert!(report.ends_with("REGRESSED"), "{report}");
+    }
+
+    #[test]
+    fn new_reverts_and_tick_counts_fail() {
+        let before = entry(200, 1000, 2000, None);
+        // Cheaper on average because it stopped early
+        let (ok, report) = entry(3, 500, 600, Some("Stack overflow")).compare(&before, 2.0);
+        assert!(!ok);
+        assert!(report.contains("ticks 200 -> 3"), "{report}");
+        assert!(report.contains("now reverts: Stack overflow"), "{report}");
+
+        let reverted = entry(3, 1000, 2000, Some("Stack overflow"));
+        let (ok, report) = entry(3, 1000, 2000, None).compare(&reverted, 2.0);
+        assert!(!ok);
+        assert!(report.contains("no longer reverts"), "{report}");
+        let (ok, _) = entry(3, 1000, 2000, Some("Invalid opcode")).compare(&reverted, 2.0);
+        assert!(!ok);
+        assert!(reverted.compare(&reverted, 0.0).0);
+    }
+}
@@ -0,0 +1,60 @@
+//! A guard against the `tick()` that would revert with "Stack overflow" or "Stack underflow".
+
+use std::fmt;
+
+use alloy::network::Network;
+use alloy::providers::Provider;
+use alloy::transports::Transport;
+
+use crate::disasm::disassemble;
+use crate::state::{read_opcode, STACK_SIZE};
+use crate::Solchip8::Solchip8Instance;
+
+/// A `tick()` that the contract would revert.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StackFault {
+    /// `2NNN` at `pc` with all `STACK_SIZE` entries in use.
+    Overflow { pc: u16, op: u16 },
+    /// `00EE` at `pc` with nothing to return to.
+    Underflow { pc: u16 },
+}
+
+impl fmt::Display for StackFault {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            StackFault::Overflow { pc, op } => write!(
+                f,
+                "{} at {pc:#05x} would push past STACK_SIZE {STACK_SIZE} (Stack overflow)",
+                disassemble(*op)
+            ),
+            StackFault::Underflow { pc } => {
+                write!(f, "RET at {pc:#05x} with an empty stack (Stack underflow)")
+            }
+        }
+    }
+}
+
+/// Check the instruction the next `tick()` executes against the stack pointer.
+pub async fn check<T, P, N>(
+    emu: &Solchip8Instance<T, P, N>,
+) -> Result<Option<StackFault>, alloy::contract::Error>
+where
+    T: Transport + Clone,
+    P: Provider<T, N>,
+    N: Network,
+{
+    let pc = emu.getPC().call().await?._0;
+    let op = read_opcode(emu, pc).await?;
+    let pushes = op & 0xF000 == 0x2000;
+    if !pushes && op != 0x00EE {
+        return Ok(None);
+    }
+    let sp = emu.getSP().call().await?._0;
+    if pushes && sp as usize >= STACK_SIZE {
+        Ok(Some(StackFault::Overflow { pc, op }))
+    } else if op == 0x00EE && sp == 0 {
+        Ok(Some(StackFault::Underflow { pc }))
+    } else {
+        Ok(None)
+    }
+}
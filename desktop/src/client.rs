@@ -0,0 +1,74 @@
+//! Connection to the node the contract runs on, and sending transactions to it.
+//!
+//! The transport follows the URL: `http://` and `https://` over HTTP, `ws://` and `wss://` over
+//! a WebSocket, and `ipc://` or a plain path over anvil's IPC socket (`anvil --ipc`). WebSocket
+//! and IPC also carry `newHeads` subscriptions.
+
+use std::str::FromStr;
+
+use alloy::contract::{CallBuilder, CallDecoder};
+use alloy::network::{EthereumWallet, Network};
+use alloy::providers::{Provider, ProviderBuilder};
+use alloy::rpc::client::BuiltInConnectionString;
+use alloy::signers::local::PrivateKeySigner;
+use alloy::transports::{BoxTransport, Transport, TransportError};
+
+/// Local anvil node.
+pub const RPC_URL: &str = "http://localhost:8545";
+
+/// Name of the transport `url` selects: `http`, `ws` or `ipc`.
+pub fn transport(url: &str) -> Result<&'static str, TransportError> {
+    Ok(match url.parse::<BuiltInConnectionString>()? {
+        BuiltInConnectionString::Http(_) => "http",
+        BuiltInConnectionString::Ws(..) => "ws",
+        BuiltInConnectionString::Ipc(_) => "ipc",
+        _ => "other",
+    })
+}
+
+/// Provider for the node at `url`, signing with the first anvil test account.
+pub async fn connect(url: &str) -> Result<impl Provider<BoxTransport> + Clone, TransportError> {
+    // testing anvil account
+    let signer: PrivateKeySigner = PrivateKeySigner::from_str(
+        "0xac0974bec39a17e36ba4a6b4d238ff944bacb478cbed5efcae784d7bf4f2ff80",
+    )
+    .unwrap();
+    let wallet = EthereumWallet::from(signer);
+
+    ProviderBuilder::new()
+        .with_recommended_fillers()
+        .wallet(wallet)
+        .on_builtin(url)
+        .await
+}
+
+/// Send a transaction and wait for it to be mined.
+///
+/// The call is simulated first so a revert surfaces with its reason (e.g. "Stack overflow")
+/// instead of as a failed receipt.
+pub async fn transact<T, P, D, N>(
+    builder: CallBuilder<T, P, D, N>,
+) -> Result<N::ReceiptResponse, alloy::contract::Error>
+where
+    T: Transport + Clone,
+    P: Provider<T, N>,
+    D: CallDecoder + Send + Sync + Unpin,
+    N: Network,
+{
+    builder.call().await?;
+    Ok(builder.send().await?.get_receipt().await?)
+}
+
+/// The message the contract reverted with, without the node's wrapping where possible.
+pub fn revert_reason(e: &alloy::contract::Error) -> String {
+    if let alloy::contract::Error::TransportError(e) = e {
+        if let Some(payload) = e.as_error_resp() {
+            let message = payload.message.as_ref();
+            return message
+                .strip_prefix("execution reverted: ")
+                .unwrap_or(message)
+                .to_string();
+        }
+    }
+    e.to_string()
+}
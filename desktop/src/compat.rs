@@ -0,0 +1,301 @@
+//! Compatibility report: every ROM in a directory on a fresh contract for a fixed number of
+//! ticks, with how each run ended, as a Markdown or JSON table.
+//!
+//! A run ends early when a `tick()` reverts, reported with the reason and the instruction at
+//! PC, or when it hangs on a `1NNN` that jumps to itself, which no input or timer can leave.
+//! `FX0A` also keeps PC in place but waits for a key, so it is not counted as a hang.
+
+use std::fmt::Write;
+use std::io;
+use std::path::Path;
+
+use alloy::network::{Network, ReceiptResponse};
+use alloy::providers::Provider;
+use alloy::transports::Transport;
+use clap::ValueEnum;
+use desktop::client;
+use desktop::input::Script;
+use serde_json::{json, Value};
+
+use crate::bench::{rom_files, GasStats};
+use crate::disasm::disassemble;
+use crate::rom::Rom;
+use crate::state::read_opcode;
+use crate::Solchip8::{self, Solchip8Instance};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum Format {
+    Markdown,
+    Json,
+}
+
+/// How a run ended.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Outcome {
+    /// Ran every tick.
+    Ok,
+    /// A `tick()` reverted on the instruction at `pc`.
+    Reverted {
+        reason: String,
+        pc: u16,
+        opcode: u16,
+    },
+    /// Stuck on the `1NNN` at `pc` jumping to itself since `tick`.
+    Hung { pc: u16, tick: u64 },
+    /// The ROM was rejected or could not be loaded.
+    Failed(String),
+}
+
+impl Outcome {
+    fn label(&self) -> &'static str {
+        match self {
+            Outcome::Ok => "ok",
+            Outcome::Reverted { .. } => "reverted",
+            Outcome::Hung { .. } => "hung",
+            Outcome::Failed(_) => "failed",
+        }
+    }
+
+    fn details(&self) -> String {
+        match self {
+            Outcome::Ok => String::new(),
+            Outcome::Reverted { reason, pc, opcode } => {
+                format!(
+                    "{reason} at {pc:#05x} `{opcode:04X} {}`",
+                    disassemble(*opcode)
+                )
+            }
+            Outcome::Hung { pc, tick } => format!("`JP {pc:#05x}` to itself from tick {tick}"),
+            Outcome::Failed(e) => e.clone(),
+        }
+    }
+
+    fn to_json(&self) -> Value {
+        match self {
+            Outcome::Ok => Value::Null,
+            Outcome::Reverted { reason, pc, opcode } => json!({
+                "reason": reason,
+                "pc": pc,
+                "opcode": opcode,
+                "instruction": disassemble(*opcode),
+            }),
+            Outcome::Hung { pc, tick } => json!({ "pc": pc, "tick": tick }),
+            Outcome::Failed(e) => json!({ "error": e }),
+        }
+    }
+}
+
+/// One row of the report.
+#[derive(Debug, Clone)]
+pub struct Report {
+    pub name: String,
+    pub outcome: Outcome,
+    /// Gas of every `tick()` that went through.
+    pub gas: Vec<u64>,
+}
+
+/// Run the program loaded in `emu` for up to `ticks` ticks, sending `input` before each tick.
+pub async fn check_rom<T, P, N>(
+    emu: &Solchip8Instance<T, P, N>,
+    ticks: u64,
+    input: &Script,
+) -> Result<(Outcome, Vec<u64>), alloy::contract::Error>
+where
+    T: Transport + Clone,
+    P: Provider<T, N>,
+    N: Network,
+{
+    let mut gas = Vec::with_capacity(ticks as usize);
+    let mut pc = emu.getPC().call().await?._0;
+    for tick in 0..ticks {
+        input.apply(tick, emu).await?;
+        match client::transact(emu.tick()).await {
+            Ok(receipt) => gas.push(receipt.gas_used() as u64),
+            Err(e) => {
+                // A reverted tick leaves the state as it was, PC still on the instruction
+                let opcode = read_opcode(emu, pc).await?;
+                let reason = client::revert_reason(&e);
+                return Ok((Outcome::Reverted { reason, pc, opcode }, gas));
+            }
+        }
+        let next = emu.getPC().call().await?._0;
+        if next == pc && read_opcode(emu, pc).await? == 0x1000 | pc {
+            return Ok((Outcome::Hung { pc, tick }, gas));
+        }
+        pc = next;
+    }
+    Ok((Outcome::Ok, gas))
+}
+
+/// Check every ROM in `dir` on its own fresh contract.
+pub async fn run<T, P, N>(
+    provider: P,
+    dir: &Path,
+    ticks: u64,
+    input: Option<Script>,
+) -> io::Result<Vec<Report>>
+where
+    T: Transport + Clone,
+    P: Provider<T, N> + Clone,
+    N: Network,
+{
+    let input = input.unwrap_or_else(|| Script::cycle_keys(ticks));
+    let mut reports = Vec::new();
+    for path in rom_files(dir)? {
+        let name = path.file_name().unwrap().to_string_lossy().to_string();
+        let (outcome, gas) = match Rom::from_file(&path) {
+            Ok(rom) => {
+                let emu = Solchip8::deploy(provider.clone())
+                    .await
+                    .map_err(|e| io::Error::other(format!("deploy failed: {e}")))?;
+                match rom.load(&emu).await {
+                    Ok(()) => check_rom(&emu, ticks, &input)
+                        .await
+                        .map_err(|e| io::Error::other(format!("{name}: {e}")))?,
+                    Err(e) => (Outcome::Failed(format!("ROM load failed: {e}")), Vec::new()),
+                }
+            }
+            Err(e) => (Outcome::Failed(e.to_string()), Vec::new()),
+        };
+        println!("{name}: {} {}", outcome.label(), outcome.details());
+        reports.push(Report { name, outcome, gas });
+    }
+    Ok(reports)
+}
+
+/// The reports as a table in `format`.
+pub fn render(reports: &[Report], ticks: u64, format: Format) -> String {
+    match format {
+        Format::Markdown => markdown(reports),
+        Format::Json => {
+            let rows: Vec<Value> = reports
+                .iter()
+                .map(|report| {
+                    let stats = GasStats::new(&report.gas);
+                    json!({
+                        "rom": report.name,
+                        "result": report.outcome.label(),
+                        "ticks": stats.ticks,
+                        "gas": { "total": stats.total, "mean": stats.mean, "p99": stats.p99 },
+                        "details": report.outcome.to_json(),
+                    })
+                })
+                .collect();
+            let report = json!({ "ticks": ticks, "roms": rows });
+            serde_json::to_string_pretty(&report).unwrap() + "\n"
+        }
+    }
+}
+
+fn markdown(reports: &[Report]) -> String {
+    let mut out = String::from(
+        "| ROM | result | ticks | mean gas | p99 gas | details |\n\
+         |-----|--------|------:|---------:|--------:|---------|\n",
+    );
+    for report in reports {
+        let stats = GasStats::new(&report.gas);
+        writeln!(
+            out,
+            "| {} | {} | {} | {} | {} | {} |",
+            report.name,
+            report.outcome.label(),
+            stats.ticks,
+            stats.mean,
+            stats.p99,
+            report.outcome.details().replace('|', "\\|")
+        )
+        .unwrap();
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn reports() -> Vec<Report> {
+        vec![
+            Report {
+                name: "PONG".to_string(),
+                outcome: Outcome::Ok,
+                gas: vec![300, 100, 200],
+            },
+            Report {
+                name: "BLITZ".to_string(),
+                outcome: Outcome::Reverted {
+                    reason: "Opcode not implemented".to_string(),
+                    pc: 0x204,
+                    opcode: 0x5121,
+                },
+                gas: vec![50],
+            },
+            Report {
+                name: "MAZE".to_string(),
+                outcome: Outcome::Hung { pc: 0x21E, tick: 7 },
+                gas: Vec::new(),
+            },
+            Report {
+                name: "EMPTY".to_string(),
+                outcome: Outcome::Failed("ROM is empty | or worse".to_string()),
+                gas: Vec::new(),
+            },
+        ]
+    }
+
+    #[test]
+    fn markdown_has_a_row_per_rom() {
+        let table = render(&reports(), 100, Format::Markdown);
+        let lines: Vec<_> = table.lines().collect();
+        assert_eq!(lines.len(), 6);
+        assert_eq!(
+            lines[0],
+            "| ROM | result | ticks | mean gas | p99 gas | details |"
+        );
+        assert_eq!(lines[2], "| PONG | ok | 3 | 200 | 300 |  |");
+        assert_eq!(
+            lines[3],
+            "| BLITZ | reverted | 1 | 50 | 50 | Opcode not implemented at 0x204 `5121 DW 0x5121` |"
+        );
+        assert_eq!(
+            lines[4],
+            "| MAZE | hung | 0 | 0 | 0 | `JP 0x21e` to itself from tick 7 |"
+        );
+        assert_eq!(
+            lines[5],
+            "| EMPTY | failed | 0 | 0 | 0 | ROM is empty \\| or worse |"
+        );
+    }
+
+    #[test]
+    fn json_keeps_the_details_structured() {
+        let table = render(&reports(), 100, Format::Json);
+        let value: Value = serde_json::from_str(&table).unwrap();
+        assert_eq!(value["ticks"], 100);
+        let roms = value["roms"].as_array().unwrap();
+        assert_eq!(roms.len(), 4);
+        assert_eq!(
+            roms[0],
+            json!({
+                "rom": "PONG",
+                "result": "ok",
+                "ticks": 3,
+                "gas": { "total": 600, "mean": 200, "p99": 300 },
+                "details": null,
+            })
+        );
+        assert_eq!(
+            roms[1]["details"],
+            json!({
+                "reason": "Opcode not implemented",
+                "pc": 0x204,
+                "opcode": 0x5121,
+                "instruction": "DW 0x5121",
+            })
+        );
+        assert_eq!(roms[2]["details"], json!({ "pc": 0x21E, "tick": 7 }));
+        assert_eq!(
+            roms[3]["details"],
+            json!({ "error": "ROM is empty | or worse" })
+        );
+    }
+}
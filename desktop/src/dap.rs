@@ -0,0 +1,769 @@
+//! Debug Adapter Protocol server, for debugging ROMs on the contract from an editor.
+//!
+//! Editors connect over TCP (`debugServer` in VS Code, a `server` adapter in nvim-dap) and
+//! `launch` with `{ "program": "path/to/ROM", "stopOnEntry": true }`, plus an optional `font`
+//! as for `--font`. The ROM is shown as a
+//! disassembly listing where line `n` is the instruction at `0x200 + 2 * (n - 1)`, so source
+//! breakpoints, instruction breakpoints and the disassembly view all map to PC addresses.
+//! Memory references are RAM addresses such as `0x200`.
+
+use std::collections::BTreeSet;
+use std::io;
+
+use alloy::network::Network;
+use alloy::primitives::U256;
+use alloy::providers::Provider;
+use alloy::transports::Transport;
+use base64::engine::general_purpose::STANDARD as BASE64;
+use base64::Engine;
+use desktop::client::transact;
+use serde_json::{json, Value};
+use tokio::io::{AsyncBufReadExt, AsyncReadExt, AsyncWriteExt, BufReader};
+use tokio::net::tcp::{OwnedReadHalf, OwnedWriteHalf};
+use tokio::net::TcpListener;
+use tokio::sync::mpsc::{self, error::TryRecvError, UnboundedReceiver, UnboundedSender};
+
+use crate::disasm::disassemble;
+use crate::expr::Expr;
+use crate::font::Font;
+use crate::rom::{Rom, RAM_SIZE, START_ADDR};
+use crate::state::Chip8State;
+use crate::storage::read_ram;
+use crate::Solchip8::{self, Solchip8Instance};
+
+const THREAD_ID: u64 = 1;
+/// `sourceReference` of the ROM disassembly listing.
+const LISTING: u64 = 1;
+
+const REGISTERS: u64 = 1;
+const TIMERS: u64 = 2;
+const STACK: u64 = 3;
+const KEYS: u64 = 4;
+
+/// Serve DAP clients on `listen`, one at a time. Each `launch` deploys a fresh contract.
+pub async fn serve<T, P, N>(provider: P, listen: &str) -> io::Result<()>
+where
+    T: Transport + Clone,
+    P: Provider<T, N> + Clone,
+    N: Network,
+{
+    let listener = TcpListener::bind(listen).await?;
+    println!("DAP server listening on {}", listener.local_addr()?);
+    loop {
+        let (stream, peer) = listener.accept().await?;
+        println!("DAP client connected from {peer}");
+        let (reader, writer) = stream.into_split();
+        let (tx, rx) = mpsc::unbounded_channel();
+        tokio::spawn(read_messages(reader, tx));
+        let mut session = Session {
+            provider: provider.clone(),
+            emu: None,
+            rom: None,
+            writer,
+            requests: rx,
+            seq: 0,
+            source_breakpoints: BTreeSet::new(),
+            instruction_breakpoints: BTreeSet::new(),
+            run: None,
+            launched: false,
+            configured: false,
+            stop_on_entry: false,
+        };
+        match session.run().await {
+            Ok(()) => println!("DAP client disconnected"),
+            Err(e) => println!("DAP session ended: {e}"),
+        }
+    }
+}
+
+/// Read `Content-Length` framed JSON messages until the client hangs up.
+async fn read_messages(reader: OwnedReadHalf, tx: UnboundedSender<Value>) -> io::Result<()> {
+    let mut reader = BufReader::new(reader);
+    loop {
+        let mut len = None;
+        loop {
+            let mut line = String::new();
+            if reader.read_line(&mut line).await? == 0 {
+                return Ok(());
+            }
+            let line = line.trim_end();
+            if line.is_empty() {
+                break;
+            }
+            if let Some(value) = line.strip_prefix("Content-Length:") {
+                len = value.trim().parse::<usize>().ok();
+            }
+        }
+        let Some(len) = len else { continue };
+        let mut body = vec![0; len];
+        reader.read_exact(&mut body).await?;
+        match serde_json::from_slice(&body) {
+            Ok(msg) => {
+                if tx.send(msg).is_err() {
+                    return Ok(());
+                }
+            }
+            Err(e) => println!("invalid DAP message: {e}"),
+        }
+    }
+}
+
+/// How execution continues while no request is pending.
+#[derive(Debug, Clone, Copy)]
+enum Run {
+    Continue,
+    /// Stop once a `2NNN` call returns to `pc` with the stack back at `sp`.
+    StepOver {
+        pc: u16,
+        sp: u16,
+    },
+    /// Stop once the stack drops below `sp`.
+    StepOut {
+        sp: u16,
+    },
+}
+
+struct Session<T, P, N> {
+    provider: P,
+    emu: Option<Solchip8Instance<T, P, N>>,
+    rom: Option<Rom>,
+    writer: OwnedWriteHalf,
+    requests: UnboundedReceiver<Value>,
+    seq: u64,
+    source_breakpoints: BTreeSet<u16>,
+    instruction_breakpoints: BTreeSet<u16>,
+    run: Option<Run>,
+    launched: bool,
+    configured: bool,
+    stop_on_entry: bool,
+}
+
+impl<T, P, N> Session<T, P, N>
+where
+    T: Transport + Clone,
+    P: Provider<T, N> + Clone,
+    N: Network,
+{
+    async fn run(&mut self) -> io::Result<()> {
+        loop {
+            let msg = if self.run.is_some() {
+                match self.requests.try_recv() {
+                    Ok(msg) => Some(msg),
+                    Err(TryRecvError::Empty) => None,
+                    Err(TryRecvError::Disconnected) => return Ok(()),
+                }
+            } else {
+                match self.requests.recv().await {
+                    Some(msg) => Some(msg),
+                    None => return Ok(()),
+                }
+            };
+            match msg {
+                Some(msg) => {
+                    if !self.handle(msg).await? {
+                        return Ok(());
+                    }
+                }
+                None => self.tick().await?,
+            }
+        }
+    }
+
+    /// Handle one request, returning `false` once the client disconnects.
+    async fn handle(&mut self, msg: Value) -> io::Result<bool> {
+        if msg["type"] != "request" {
+            return Ok(true);
+        }
+        let command = msg["command"].as_str().unwrap_or_default().to_string();
+        let args = &msg["arguments"];
+        let result = match command.as_str() {
+            "initialize" => {
+                let capabilities = json!({
+                    "supportsConfigurationDoneRequest": true,
+                    "supportsDisassembleRequest": true,
+                    "supportsInstructionBreakpoints": true,
+                    "supportsReadMemoryRequest": true,
+                    "supportsWriteMemoryRequest": true,
+                    "supportsSetVariable": true,
+                    "supportsEvaluateForHovers": true,
+                    "supportsTerminateRequest": true,
+                });
+                self.respond(&msg, Ok(capabilities)).await?;
+                self.event("initialized", json!({})).await?;
+                return Ok(true);
+            }
+            "launch" => {
+                let result = self.launch(args).await;
+                let launched = result.is_ok();
+                self.respond(&msg, result).await?;
+                if launched {
+                    self.start().await?;
+                }
+                return Ok(true);
+            }
+            "configurationDone" => {
+                self.configured = true;
+                self.respond(&msg, Ok(Value::Null)).await?;
+                self.start().await?;
+                return Ok(true);
+            }
+            "setBreakpoints" => self.set_breakpoints(args),
+            "setInstructionBreakpoints" => self.set_instruction_breakpoints(args),
+            "setExceptionBreakpoints" => Ok(json!({ "breakpoints": [] })),
+            "threads" => Ok(json!({ "threads": [{ "id": THREAD_ID, "name": "CHIP-8" }] })),
+            "stackTrace" => self.stack_trace().await,
+            "scopes" => Ok(json!({ "scopes": [
+                { "name": "Registers", "variablesReference": REGISTERS, "expensive": false },
+                { "name": "Timers", "variablesReference": TIMERS, "expensive": false },
+                { "name": "Stack", "variablesReference": STACK, "expensive": false },
+                { "name": "Keys", "variablesReference": KEYS, "expensive": false },
+            ]})),
+            "variables" => self.variables(args).await,
+            "setVariable" => self.set_variable(args).await,
+            "evaluate" => self.evaluate(args).await,
+            "source" => self.source(),
+            "readMemory" => self.read_memory(args).await,
+            "writeMemory" => self.write_memory(args).await,
+            "disassemble" => self.disassemble(args).await,
+            "continue" => {
+                self.run = Some(Run::Continue);
+                Ok(json!({ "allThreadsContinued": true }))
+            }
+            "next" => {
+                let state = self.state().await;
+                let is_call = matches!(&state, Ok(state) if state.opcode & 0xF000 == 0x2000);
+                self.respond(&msg, state.map(|_| Value::Null)).await?;
+                if is_call {
+                    self.step_over().await?;
+                } else {
+                    self.step().await?;
+                }
+                return Ok(true);
+            }
+            "stepIn" => {
+                self.respond(&msg, Ok(Value::Null)).await?;
+                self.step().await?;
+                return Ok(true);
+            }
+            "stepOut" => match self.state().await {
+                Ok(state) if state.sp > 0 => {
+                    self.run = Some(Run::StepOut { sp: state.sp });
+                    Ok(Value::Null)
+                }
+                Ok(_) => Err("not inside a subroutine".to_string()),
+                Err(e) => Err(e),
+            },
+            "pause" => {
+                self.respond(&msg, Ok(Value::Null)).await?;
+                self.stop("pause", None).await?;
+                return Ok(true);
+            }
+            "disconnect" | "terminate" => {
+                self.respond(&msg, Ok(Value::Null)).await?;
+                self.event("terminated", json!({})).await?;
+                return Ok(false);
+            }
+            _ => Err(format!("unsupported request `{command}`")),
+        };
+        self.respond(&msg, result).await?;
+        Ok(true)
+    }
+
+    async fn launch(&mut self, args: &Value) -> Result<Value, String> {
+        let path = args["program"]
+            .as_str()
+            .ok_or("launch needs a `program` with the ROM path")?;
+        let rom = Rom::from_file(path).map_err(|e| format!("invalid ROM {path}: {e}"))?;
+        let emu = Solchip8::deploy(self.provider.clone())
+            .await
+            .map_err(|e| format!("deploy failed: {e}"))?;
+        rom.load(&emu)
+            .await
+            .map_err(|e| format!("ROM load failed: {e}"))?;
+        if let Some(spec) = args["font"].as_str() {
+            let font = Font::load(spec).map_err(|e| format!("invalid font {spec}: {e}"))?;
+            font.install(&emu)
+                .await
+                .map_err(|e| format!("font install failed: {e}"))?;
+        }
+        println!("Launched {rom}");
+
+        self.emu = Some(emu);
+        self.rom = Some(rom);
+        self.stop_on_entry = args["stopOnEntry"].as_bool().unwrap_or(false);
+        self.launched = true;
+        Ok(Value::Null)
+    }
+
+    /// Begin execution once both `launch` and `configurationDone` have arrived.
+    async fn start(&mut self) -> io::Result<()> {
+        if !(self.launched && self.configured) {
+            return Ok(());
+        }
+        if self.stop_on_entry {
+            self.stop("entry", None).await
+        } else {
+            self.run = Some(Run::Continue);
+            Ok(())
+        }
+    }
+
+    fn set_breakpoints(&mut self, args: &Value) -> Result<Value, String> {
+        let lines: Vec<u64> = args["breakpoints"]
+            .as_array()
+            .map(|bps| bps.iter().filter_map(|bp| bp["line"].as_u64()).collect())
+            .unwrap_or_default();
+        self.source_breakpoints = lines.iter().map(|line| line_to_addr(*line)).collect();
+        let breakpoints: Vec<_> = lines
+            .iter()
+            .map(|line| {
+                json!({
+                    "verified": true,
+                    "line": line,
+                    "instructionReference": format!("{:#05x}", line_to_addr(*line)),
+                })
+            })
+            .collect();
+        Ok(json!({ "breakpoints": breakpoints }))
+    }
+
+    fn set_instruction_breakpoints(&mut self, args: &Value) -> Result<Value, String> {
+        let mut breakpoints = Vec::new();
+        self.instruction_breakpoints.clear();
+        for bp in args["breakpoints"].as_array().into_iter().flatten() {
+            let addr = bp["instructionReference"]
+                .as_str()
+                .and_then(parse_reference)
+                .map(|addr| addr + bp["offset"].as_i64().unwrap_or(0));
+            match addr {
+                Some(addr) if (0..RAM_SIZE as i64).contains(&addr) => {
+                    self.instruction_breakpoints.insert(addr as u16);
+                    breakpoints.push(json!({
+                        "verified": true,
+                        "instructionReference": format!("{addr:#05x}"),
+                    }));
+                }
+                _ => breakpoints.push(json!({ "verified": false })),
+            }
+        }
+        Ok(json!({ "breakpoints": breakpoints }))
+    }
+
+    /// The top frame is the PC, the frames below are the call sites on the contract stack.
+    async fn stack_trace(&self) -> Result<Value, String> {
+        let state = self.state().await?;
+        let mut frames = vec![self.frame(0, state.pc, state.opcode)];
+        let depth = (state.sp as usize).min(state.stack.len());
+        for (id, ret) in state.stack[..depth].iter().rev().enumerate() {
+            let call = ret.wrapping_sub(2);
+            let op = self.rom_opcode(call).unwrap_or(0x2000);
+            frames.push(self.frame(id + 1, call, op));
+        }
+        Ok(json!({ "stackFrames": frames, "totalFrames": frames.len() }))
+    }
+
+    fn frame(&self, id: usize, addr: u16, op: u16) -> Value {
+        json!({
+            "id": id,
+            "name": format!("{addr:#05x} {}", disassemble(op)),
+            "source": self.listing_source(),
+            "line": addr_to_line(addr),
+            "column": 1,
+            "instructionPointerReference": format!("{addr:#05x}"),
+        })
+    }
+
+    fn listing_source(&self) -> Value {
+        let name = self
+            .rom
+            .as_ref()
+            .and_then(|rom| rom.title())
+            .unwrap_or("ROM");
+        json!({ "name": format!("{name}.dis"), "sourceReference": LISTING })
+    }
+
+    async fn variables(&self, args: &Value) -> Result<Value, String> {
+        let state = self.state().await?;
+        let var = |name: String, value: String| json!({ "name": name, "value": value, "variablesReference": 0 });
+        let variables: Vec<_> = match args["variablesReference"].as_u64() {
+            Some(REGISTERS) => {
+                let mut vars: Vec<_> = state
+                    .v
+                    .iter()
+                    .enumerate()
+                    .map(|(x, v)| var(format!("V{x:X}"), format!("{v:#04x}")))
+                    .collect();
+                vars.push(json!({
+                    "name": "I",
+                    "value": format!("{:#05x}", state.i),
+                    "variablesReference": 0,
+                    "memoryReference": format!("{:#05x}", state.i),
+                }));
+                vars.push(json!({
+                    "name": "PC",
+                    "value": format!("{:#05x}", state.pc),
+                    "variablesReference": 0,
+                    "memoryReference": format!("{:#05x}", state.pc),
+                }));
+                vars.push(var("SP".into(), state.sp.to_string()));
+                vars
+            }
+            Some(TIMERS) => vec![
+                var("DT".into(), state.dt.to_string()),
+                var("ST".into(), state.st.to_string()),
+            ],
+            Some(STACK) => state
+                .stack
+                .iter()
+                .take((state.sp as usize).min(state.stack.len()))
+                .enumerate()
+                .map(|(i, ret)| var(format!("[{i}]"), format!("{ret:#05x}")))
+                .collect(),
+            Some(KEYS) => (0..16)
+                .map(|k| var(format!("{k:X}"), (state.keys & (1 << k) != 0).to_string()))
+                .collect(),
+            _ => return Err("unknown variablesReference".to_string()),
+        };
+        Ok(json!({ "variables": variables }))
+    }
+
+    async fn set_variable(&self, args: &Value) -> Result<Value, String> {
+        let emu = self.emu()?;
+        let name = args["name"].as_str().unwrap_or_default();
+        let value = args["value"].as_str().unwrap_or_default();
+        let value = parse_number(value).ok_or(format!("invalid value `{value}`"))?;
+        let result = match (args["variablesReference"].as_u64(), name) {
+            (Some(REGISTERS), "I") => transact(emu.setIRegister(value as u16)).await,
+            (Some(REGISTERS), "SP") => transact(emu.setSP(value as u16)).await,
+            (Some(REGISTERS), "PC") => return Err("the contract has no way to set PC".into()),
+            (Some(REGISTERS), v) if v.starts_with('V') => {
+                let x = u8::from_str_radix(&v[1..], 16).map_err(|_| "unknown register")?;
+                transact(emu.setVRegister(x, value as u8)).await
+            }
+            (Some(TIMERS), "DT") => transact(emu.setDelayTimer(value as u8)).await,
+            (Some(TIMERS), "ST") => transact(emu.setSoundTimer(value as u8)).await,
+            (Some(STACK), _) => {
+                let idx: usize = name
+                    .trim_matches(|c| c == '[' || c == ']')
+                    .parse()
+                    .map_err(|_| "unknown stack entry")?;
+                transact(emu.setStackValue(U256::from(idx), value as u16)).await
+            }
+            _ => return Err(format!("`{name}` cannot be set")),
+        };
+        result.map_err(|e| e.to_string())?;
+        Ok(json!({ "value": format!("{value:#x}") }))
+    }
+
+    /// Evaluate a debugger expression such as `V3 + 1` or `[I]`.
+    async fn evaluate(&self, args: &Value) -> Result<Value, String> {
+        let expr: Expr = args["expression"].as_str().unwrap_or_default().parse()?;
+        let state = self.state().await?;
+        let mut ram = std::collections::BTreeMap::new();
+        let value = loop {
+            match expr.eval(&state, &ram) {
+                Ok(value) => break value,
+                Err(addr) => {
+                    let byte = read_ram(self.emu()?, addr as usize, 1)
+                        .await
+                        .map_err(|e| e.to_string())?;
+                    ram.insert(addr, byte[0]);
+                }
+            }
+        };
+        Ok(json!({ "result": format!("{value:#x} ({value})"), "variablesReference": 0 }))
+    }
+
+    fn source(&self) -> Result<Value, String> {
+        let rom = self.rom.as_ref().ok_or("no ROM launched")?;
+        let content: Vec<_> = rom
+            .bytes()
+            .chunks(2)
+            .enumerate()
+            .map(|(i, pair)| {
+                let op = u16::from_be_bytes([pair[0], *pair.get(1).unwrap_or(&0)]);
+                format!("{:#05x}  {op:04X}  {}", START_ADDR + i * 2, disassemble(op))
+            })
+            .collect();
+        Ok(json!({ "content": content.join("\n"), "mimeType": "text/x-chip8" }))
+    }
+
+    async fn read_memory(&self, args: &Value) -> Result<Value, String> {
+        let addr = memory_address(args)?;
+        let count = args["count"].as_u64().unwrap_or(0) as usize;
+        let readable = if (0..RAM_SIZE as i64).contains(&addr) {
+            let count = count.min(RAM_SIZE - addr as usize);
+            read_ram(self.emu()?, addr as usize, count)
+                .await
+                .map_err(|e| e.to_string())?
+        } else {
+            Vec::new()
+        };
+        Ok(json!({
+            "address": format!("{addr:#05x}"),
+            "data": BASE64.encode(&readable),
+            "unreadableBytes": count - readable.len(),
+        }))
+    }
+
+    async fn write_memory(&self, args: &Value) -> Result<Value, String> {
+        let addr = memory_address(args)?;
+        let data = BASE64
+            .decode(args["data"].as_str().unwrap_or_default())
+            .map_err(|e| e.to_string())?;
+        let end = usize::try_from(addr)
+            .ok()
+            .and_then(|addr| addr.checked_add(data.len()));
+        if end.is_none_or(|end| end > RAM_SIZE) {
+            return Err("write outside RAM".to_string());
+        }
+        let emu = self.emu()?;
+        for (i, byte) in data.iter().enumerate() {
+            transact(emu.setRAMValueAt(U256::from(addr as usize + i), *byte))
+                .await
+                .map_err(|e| e.to_string())?;
+        }
+        Ok(json!({ "bytesWritten": data.len() }))
+    }
+
+    async fn disassemble(&self, args: &Value) -> Result<Value, String> {
+        let offset = args["instructionOffset"].as_i64().unwrap_or(0);
+        let base = memory_address(args)?.saturating_add(offset.saturating_mul(2));
+        // No more than RAM holds, the rest would all be `??`
+        let count = args["instructionCount"]
+            .as_u64()
+            .unwrap_or(0)
+            .min(RAM_SIZE as u64) as i64;
+        let first = base.clamp(0, RAM_SIZE as i64) as usize;
+        let last = base.saturating_add(count * 2).clamp(0, RAM_SIZE as i64) as usize;
+        let ram = read_ram(self.emu()?, first, last - first)
+            .await
+            .map_err(|e| e.to_string())?;
+
+        let instructions: Vec<_> = (0..count)
+            .map(|n| {
+                let addr = base.saturating_add(n * 2);
+                match opcode_at(&ram, first, addr) {
+                    Some(op) => {
+                        json!({
+                            "address": format!("{addr:#05x}"),
+                            "instructionBytes": format!("{op:04X}"),
+                            "instruction": disassemble(op),
+                            "location": self.listing_source(),
+                            "line": addr_to_line(addr as u16),
+                        })
+                    }
+                    _ => json!({
+                        "address": format!("{addr:#x}"),
+                        "instruction": "??",
+                        "presentationHint": "invalid",
+                    }),
+                }
+            })
+            .collect();
+        Ok(json!({ "instructions": instructions }))
+    }
+
+    /// Run until the `2NNN` call at PC returns.
+    async fn step_over(&mut self) -> io::Result<()> {
+        match self.state().await {
+            Ok(state) => {
+                self.run = Some(Run::StepOver {
+                    pc: state.pc + 2,
+                    sp: state.sp,
+                });
+                Ok(())
+            }
+            Err(e) => self.stop("exception", Some(e)).await,
+        }
+    }
+
+    async fn step(&mut self) -> io::Result<()> {
+        self.run = None;
+        if self.tick_once().await? {
+            self.stop("step", None).await?;
+        }
+        Ok(())
+    }
+
+    /// Run one `tick()` for the running session and stop if a breakpoint or target is reached.
+    async fn tick(&mut self) -> io::Result<()> {
+        let Some(run) = self.run else { return Ok(()) };
+        if !self.tick_once().await? {
+            return Ok(());
+        }
+        let (pc, sp) = {
+            let Ok(emu) = self.emu() else { return Ok(()) };
+            match (emu.getPC().call().await, emu.getSP().call().await) {
+                (Ok(pc), Ok(sp)) => (pc._0, sp._0),
+                (Err(e), _) | (_, Err(e)) => {
+                    return self.stop("exception", Some(e.to_string())).await;
+                }
+            }
+        };
+        if self.source_breakpoints.contains(&pc) || self.instruction_breakpoints.contains(&pc) {
+            let reason = if self.source_breakpoints.contains(&pc) {
+                "breakpoint"
+            } else {
+                "instruction breakpoint"
+            };
+            return self.stop(reason, None).await;
+        }
+        match run {
+            Run::StepOver {
+                pc: target,
+                sp: depth,
+            } if pc == target && sp == depth => self.stop("step", None).await,
+            Run::StepOut { sp: depth } if sp < depth => self.stop("step", None).await,
+            _ => Ok(()),
+        }
+    }
+
+    /// Send a `tick()`, reporting a revert as an exception stop. Returns whether it succeeded.
+    async fn tick_once(&mut self) -> io::Result<bool> {
+        let result = match self.emu() {
+            Ok(emu) => transact(emu.tick()).await.map_err(|e| e.to_string()),
+            Err(e) => Err(e),
+        };
+        match result {
+            Ok(_) => Ok(true),
+            Err(e) => {
+                self.stop("exception", Some(format!("tick() reverted: {e}")))
+                    .await?;
+                Ok(false)
+            }
+        }
+    }
+
+    async fn stop(&mut self, reason: &str, text: Option<String>) -> io::Result<()> {
+        self.run = None;
+        if let Some(text) = &text {
+            self.event(
+                "output",
+                json!({ "category": "console", "output": format!("{text}\n") }),
+            )
+            .await?;
+        }
+        self.event(
+            "stopped",
+            json!({
+                "reason": reason,
+                "description": text,
+                "threadId": THREAD_ID,
+                "allThreadsStopped": true,
+            }),
+        )
+        .await
+    }
+
+    fn emu(&self) -> Result<&Solchip8Instance<T, P, N>, String> {
+        self.emu.as_ref().ok_or("no ROM launched".to_string())
+    }
+
+    async fn state(&self) -> Result<Chip8State, String> {
+        Chip8State::fetch(self.emu()?)
+            .await
+            .map_err(|e| e.to_string())
+    }
+
+    /// Opcode at `addr` in the launched ROM, for labelling call sites without an RPC round trip.
+    fn rom_opcode(&self, addr: u16) -> Option<u16> {
+        let bytes = self.rom.as_ref()?.bytes();
+        let offset = (addr as usize).checked_sub(START_ADDR)?;
+        Some(u16::from_be_bytes([
+            *bytes.get(offset)?,
+            *bytes.get(offset + 1)?,
+        ]))
+    }
+
+    async fn respond(&mut self, request: &Value, result: Result<Value, String>) -> io::Result<()> {
+        let mut response = json!({
+            "type": "response",
+            "request_seq": request["seq"],
+            "command": request["command"],
+            "success": result.is_ok(),
+        });
+        match result {
+            Ok(Value::Null) => {}
+            Ok(body) => response["body"] = body,
+            Err(message) => response["message"] = message.into(),
+        }
+        self.send(response).await
+    }
+
+    async fn event(&mut self, event: &str, body: Value) -> io::Result<()> {
+        self.send(json!({ "type": "event", "event": event, "body": body }))
+            .await
+    }
+
+    async fn send(&mut self, mut msg: Value) -> io::Result<()> {
+        self.seq += 1;
+        msg["seq"] = self.seq.into();
+        let body = msg.to_string();
+        let packet = format!("Content-Length: {}\r\n\r\n{}", body.len(), body);
+        self.writer.write_all(packet.as_bytes()).await
+    }
+}
+
+fn line_to_addr(line: u64) -> u16 {
+    (START_ADDR as u64 + line.saturating_sub(1) * 2) as u16
+}
+
+fn addr_to_line(addr: u16) -> i64 {
+    (addr as i64 - START_ADDR as i64) / 2 + 1
+}
+
+fn memory_address(args: &Value) -> Result<i64, String> {
+    let reference = args["memoryReference"].as_str().unwrap_or_default();
+    let addr =
+        parse_reference(reference).ok_or(format!("invalid memoryReference `{reference}`"))?;
+    Ok(addr.saturating_add(args["offset"].as_i64().unwrap_or(0)))
+}
+
+/// Opcode at `addr` in `ram`, which holds RAM from `first`; `None` outside it.
+fn opcode_at(ram: &[u8], first: usize, addr: i64) -> Option<u16> {
+    let offset = usize::try_from(addr.checked_sub(first as i64)?).ok()?;
+    let bytes = ram.get(offset..offset.checked_add(2)?)?;
+    Some(u16::from_be_bytes([bytes[0], bytes[1]]))
+}
+
+fn parse_reference(s: &str) -> Option<i64> {
+    parse_number(s).map(|n| n as i64)
+}
+
+fn parse_number(s: &str) -> Option<u32> {
+    let s = s.trim();
+    match s.strip_prefix("0x").or(s.strip_prefix("0X")) {
+        Some(hex) => u32::from_str_radix(hex, 16).ok(),
+        None => s.parse().ok(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn opcodes_outside_the_read_are_invalid() {
+        let ram = [0x12, 0x00, 0xA2, 0x2A, 0x60];
+        assert_eq!(opcode_at(&ram, 0x200, 0x200), Some(0x1200));
+        assert_eq!(opcode_at(&ram, 0x200, 0x201), Some(0x00A2));
+        assert_eq!(opcode_at(&ram, 0x200, 0x202), Some(0xA22A));
+        // One byte short at the end, before the start and far out of range
+        assert_eq!(opcode_at(&ram, 0x200, 0x204), None);
+        assert_eq!(opcode_at(&ram, 0x200, 0x1ff), None);
+        assert_eq!(opcode_at(&ram, 0, -1), None);
+        assert_eq!(opcode_at(&ram, 0, i64::MIN), None);
+        assert_eq!(opcode_at(&ram, 0, i64::MAX), None);
+    }
+
+    #[test]
+    fn memory_addresses_saturate() {
+        let args = json!({ "memoryReference": "0x200", "offset": -2 });
+        assert_eq!(memory_address(&args), Ok(0x1fe));
+        let args = json!({ "memoryReference": "0xffffffff", "offset": i64::MAX });
+        assert_eq!(memory_address(&args), Ok(i64::MAX));
+        let args = json!({ "memoryReference": "pc" });
+        assert!(memory_address(&args).is_err());
+    }
+}
@@ -0,0 +1,674 @@
+//! Interactive debugger driving the contract one `tick()` at a time.
+//!
+//! Commands are read from stdin while the SDL window keeps running, see `HELP`.
+//! F5 (pause/continue), F10 (step over) and F11 (step) work from the window too.
+
+use std::collections::BTreeMap;
+use std::fmt;
+use std::io::BufRead;
+use std::str::FromStr;
+use std::sync::mpsc::{self, Receiver};
+use std::thread;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use alloy::network::Network;
+use alloy::primitives::U256;
+use alloy::providers::Provider;
+use alloy::transports::Transport;
+use desktop::client;
+use desktop::random;
+
+use crate::callstack::StackFault;
+use crate::determinism::{self, Head};
+use crate::disasm::disassemble;
+use crate::expr::{Expr, Reg};
+use crate::font::Font;
+use crate::rom::RAM_SIZE;
+use crate::state::{read_opcode, Chip8State, STACK_SIZE};
+use crate::storage;
+use crate::Solchip8::Solchip8Instance;
+
+const HELP: &str = "\
+commands:
+  p            pause                 c            continue
+  s            step one tick()       n            step over 2NNN calls
+  u <addr>     run to address        b <addr>     set breakpoint on PC
+  d <addr>     delete breakpoint     l            list breakpoints
+  r            print registers       bt           print call stack
+  reset        reset() the contract, reinstalling --font
+  h            help
+  b <addr> if <expr>                 break on PC when <expr> holds
+  cond <expr>  break when <expr> becomes true, e.g. `V3 == 0x10 && I > 0x300`
+  w <reg>      watch a register      w <addr>[..<end>]  watch RAM bytes
+  dw <n>       delete watchpoint     dc <n>       delete condition";
+
+/// An expression together with the text it was parsed from, for listing.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Condition {
+    source: String,
+    expr: Expr,
+}
+
+impl FromStr for Condition {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Ok(Self {
+            source: s.trim().to_string(),
+            expr: s.parse()?,
+        })
+    }
+}
+
+/// What a watchpoint observes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum WatchTarget {
+    Reg(Reg),
+    /// RAM bytes `start..end`.
+    Ram(u16, u16),
+}
+
+impl FromStr for WatchTarget {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if let Ok(reg) = s.parse() {
+            return Ok(WatchTarget::Reg(reg));
+        }
+        let (start, end) = match s.split_once("..") {
+            Some((start, end)) => (parse_addr(start)?, parse_addr(end)?),
+            None => {
+                let addr = parse_addr(s)?;
+                let end = addr
+                    .checked_add(1)
+                    .ok_or(format!("invalid RAM range `{s}`"))?;
+                (addr, end)
+            }
+        };
+        if start >= end || end as usize > RAM_SIZE {
+            return Err(format!("invalid RAM range `{s}`"));
+        }
+        Ok(WatchTarget::Ram(start, end))
+    }
+}
+
+impl fmt::Display for WatchTarget {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            WatchTarget::Reg(reg) => write!(f, "{reg}"),
+            WatchTarget::Ram(start, end) if end - start == 1 => write!(f, "[{start:#05x}]"),
+            WatchTarget::Ram(start, end) => write!(f, "[{start:#05x}..{end:#05x}]"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Command {
+    Pause,
+    Continue,
+    Step,
+    StepOver,
+    RunTo(u16),
+    Break(u16, Option<Condition>),
+    Delete(u16),
+    Cond(Condition),
+    DeleteCond(usize),
+    Watch(WatchTarget),
+    DeleteWatch(usize),
+    List,
+    Registers,
+    Backtrace,
+    Reset,
+    Help,
+}
+
+impl FromStr for Command {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s = s.trim();
+        let (cmd, args) = s.split_once(char::is_whitespace).unwrap_or((s, ""));
+        let args = args.trim();
+        let addr = |args: &str| match args.split_whitespace().collect::<Vec<_>>()[..] {
+            [] => Err(format!("`{cmd}` needs an address")),
+            [addr] => parse_addr(addr),
+            [_, extra, ..] => Err(format!("unexpected `{extra}` after the address")),
+        };
+        let index = || {
+            args.parse()
+                .map_err(|_| format!("`{cmd}` needs a number from `l`"))
+        };
+        match cmd {
+            "p" | "pause" => Ok(Command::Pause),
+            "c" | "continue" => Ok(Command::Continue),
+            "s" | "step" => Ok(Command::Step),
+            "n" | "next" => Ok(Command::StepOver),
+            "u" | "until" => Ok(Command::RunTo(addr(args)?)),
+            "b" | "break" => {
+                let (target, cond) = match args.split_once(" if ") {
+                    Some((target, cond)) => (target, Some(cond.parse()?)),
+                    None => (args, None),
+                };
+                Ok(Command::Break(addr(target)?, cond))
+            }
+            "d" | "delete" => Ok(Command::Delete(addr(args)?)),
+            "cond" => Ok(Command::Cond(args.parse()?)),
+            "dc" => Ok(Command::DeleteCond(index()?)),
+            "w" | "watch" => Ok(Command::Watch(args.parse()?)),
+            "dw" => Ok(Command::DeleteWatch(index()?)),
+            "l" | "list" => Ok(Command::List),
+            "r" | "regs" => Ok(Command::Registers),
+            "bt" | "backtrace" => Ok(Command::Backtrace),
+            "reset" => Ok(Command::Reset),
+            "h" | "help" => Ok(Command::Help),
+            _ => Err(format!("unknown command `{cmd}`, `h` for help")),
+        }
+    }
+}
+
+/// Parse a hex address, with or without a `0x` prefix.
+pub fn parse_addr(s: &str) -> Result<u16, String> {
+    let digits = s.trim_start_matches("0x").trim_start_matches("0X");
+    u16::from_str_radix(digits, 16).map_err(|e| format!("invalid address `{s}`: {e}"))
+}
+
+/// Temporary stop set by step-over and run-to.
+#[derive(Debug, Clone, Copy)]
+struct Target {
+    pc: u16,
+    /// Only stop at `pc` once the stack is back at this depth, so recursive calls are stepped over.
+    sp: Option<u16>,
+}
+
+struct Watchpoint {
+    target: WatchTarget,
+    /// Values after the last tick, one per register or RAM byte.
+    last: Vec<u32>,
+}
+
+struct Trigger {
+    cond: Condition,
+    /// Conditions only break on the step where they become true.
+    was_true: bool,
+}
+
+pub struct Debugger {
+    paused: bool,
+    step: bool,
+    breakpoints: BTreeMap<u16, Option<Condition>>,
+    conditions: Vec<Trigger>,
+    watchpoints: Vec<Watchpoint>,
+    target: Option<Target>,
+    /// Font to reinstall after `reset`.
+    font: Option<Font>,
+    /// Blocks are pinned, so `CXNN` predictions are exact.
+    pinned: bool,
+    commands: Receiver<Command>,
+}
+
+impl Debugger {
+    /// Start a debugger, paused before the first tick, and spawn the stdin reader.
+    pub fn new(font: Option<Font>, pinned: bool) -> Self {
+        let (tx, rx) = mpsc::channel();
+        thread::spawn(move || {
+            for line in std::io::stdin().lock().lines() {
+                let Ok(line) = line else { break };
+                if line.trim().is_empty() {
+                    continue;
+                }
+                match line.parse() {
+                    Ok(cmd) => {
+                        if tx.send(cmd).is_err() {
+                            break;
+                        }
+                    }
+                    Err(e) => println!("{e}"),
+                }
+            }
+        });
+        println!("{HELP}");
+
+        Self {
+            paused: true,
+            step: false,
+            breakpoints: BTreeMap::new(),
+            conditions: Vec::new(),
+            watchpoints: Vec::new(),
+            target: None,
+            font,
+            pinned,
+            commands: rx,
+        }
+    }
+
+    /// Handle every command typed on stdin since the last call.
+    pub async fn poll<T, P, N>(&mut self, emu: &Solchip8Instance<T, P, N>)
+    where
+        T: Transport + Clone,
+        P: Provider<T, N>,
+        N: Network,
+    {
+        while let Ok(cmd) = self.commands.try_recv() {
+            if let Err(e) = self.command(cmd, emu).await {
+                println!("{e}");
+            }
+        }
+    }
+
+    /// Run one command. Errors reading the contract are returned for the caller to print, the
+    /// session goes on.
+    pub async fn command<T, P, N>(
+        &mut self,
+        cmd: Command,
+        emu: &Solchip8Instance<T, P, N>,
+    ) -> Result<(), alloy::contract::Error>
+    where
+        T: Transport + Clone,
+        P: Provider<T, N>,
+        N: Network,
+    {
+        match cmd {
+            Command::Pause => {
+                self.paused = true;
+                self.target = None;
+                self.print_state(emu, "paused").await?;
+            }
+            Command::Continue => {
+                self.paused = false;
+            }
+            Command::Step => {
+                self.step = true;
+            }
+            Command::StepOver => {
+                let state = Chip8State::fetch(emu).await?;
+                if state.opcode & 0xF000 == 0x2000 {
+                    self.target = Some(Target {
+                        pc: state.pc + 2,
+                        sp: Some(state.sp),
+                    });
+                    self.paused = false;
+                } else {
+                    self.step = true;
+                }
+            }
+            Command::RunTo(pc) => {
+                self.target = Some(Target { pc, sp: None });
+                self.paused = false;
+            }
+            Command::Break(pc, cond) => {
+                match &cond {
+                    Some(cond) => println!("breakpoint at {pc:#05x} if {}", cond.source),
+                    None => println!("breakpoint at {pc:#05x}"),
+                }
+                self.breakpoints.insert(pc, cond);
+            }
+            Command::Delete(pc) => {
+                if self.breakpoints.remove(&pc).is_none() {
+                    println!("no breakpoint at {pc:#05x}");
+                }
+            }
+            Command::Cond(cond) => {
+                let state = Chip8State::fetch(emu).await?;
+                match cond.expr.holds(&state, emu).await {
+                    Ok(was_true) => self.conditions.push(Trigger { cond, was_true }),
+                    Err(e) => println!("condition {} failed: {e}", cond.source),
+                }
+            }
+            Command::DeleteCond(n) => {
+                if n == 0 || n > self.conditions.len() {
+                    println!("no condition #{n}");
+                } else {
+                    self.conditions.remove(n - 1);
+                }
+            }
+            Command::Watch(target) => {
+                let state = Chip8State::fetch(emu).await?;
+                let last = read_watch(target, &state, emu).await?;
+                self.watchpoints.push(Watchpoint { target, last });
+            }
+            Command::DeleteWatch(n) => {
+                if n == 0 || n > self.watchpoints.len() {
+                    println!("no watchpoint #{n}");
+                } else {
+                    self.watchpoints.remove(n - 1);
+                }
+            }
+            Command::List => {
+                for (pc, cond) in &self.breakpoints {
+                    match cond {
+                        Some(cond) => println!("breakpoint at {pc:#05x} if {}", cond.source),
+                        None => println!("breakpoint at {pc:#05x}"),
+                    }
+                }
+                for (n, trigger) in self.conditions.iter().enumerate() {
+                    println!("condition #{}: {}", n + 1, trigger.cond.source);
+                }
+                for (n, watch) in self.watchpoints.iter().enumerate() {
+                    println!("watchpoint #{}: {}", n + 1, watch.target);
+                }
+            }
+            Command::Registers => self.print_state(emu, "registers").await?,
+            Command::Backtrace => {
+                println!("{}", CallStack::fetch(emu).await?);
+            }
+            Command::Reset => {
+                if let Err(e) = client::transact(emu.reset()).await {
+                    println!("reset failed: {e}");
+                    return Ok(());
+                }
+                // reset() copies the contract's FONTSET back over RAM 0x000-0x04F
+                if let Some(font) = &self.font {
+                    if let Err(e) = font.install(emu).await {
+                        println!("font install failed: {e}");
+                    }
+                }
+                self.print_state(emu, "reset").await?;
+            }
+            Command::Help => println!("{HELP}"),
+        }
+        Ok(())
+    }
+
+    /// Toggle between paused and running, as bound to F5.
+    pub fn toggle_pause(&self) -> Command {
+        if self.paused {
+            Command::Continue
+        } else {
+            Command::Pause
+        }
+    }
+
+    /// Whether the game loop may send the next `tick()`.
+    pub fn should_tick(&mut self) -> bool {
+        if !self.paused {
+            return true;
+        }
+        std::mem::take(&mut self.step)
+    }
+
+    /// Pause instead of sending a `tick()` the contract would revert.
+    pub async fn fault<T, P, N>(
+        &mut self,
+        fault: StackFault,
+        emu: &Solchip8Instance<T, P, N>,
+    ) -> Result<(), alloy::contract::Error>
+    where
+        T: Transport + Clone,
+        P: Provider<T, N>,
+        N: Network,
+    {
+        self.paused = true;
+        self.target = None;
+        println!("── {fault} ──");
+        println!("{}", CallStack::fetch(emu).await?);
+        Ok(())
+    }
+
+    /// Check stop conditions against the state after a `tick()` and refresh the register panel.
+    pub async fn after_tick<T, P, N>(
+        &mut self,
+        emu: &Solchip8Instance<T, P, N>,
+    ) -> Result<(), alloy::contract::Error>
+    where
+        T: Transport + Clone,
+        P: Provider<T, N>,
+        N: Network,
+    {
+        let state = Chip8State::fetch(emu).await?;
+
+        let mut reasons = Vec::new();
+        if let Some(target) = self.target {
+            if state.pc == target.pc && target.sp.is_none_or(|sp| state.sp == sp) {
+                reasons.push(format!("reached {:#05x}", target.pc));
+            }
+        }
+        // A condition that can't be evaluated stops, so the failure is seen where it happened
+        match self.breakpoints.get(&state.pc) {
+            Some(None) => reasons.push(format!("breakpoint at {:#05x}", state.pc)),
+            Some(Some(cond)) => match cond.expr.holds(&state, emu).await {
+                Ok(true) => reasons.push(format!(
+                    "breakpoint at {:#05x} if {}",
+                    state.pc, cond.source
+                )),
+                Ok(false) => {}
+                Err(e) => reasons.push(format!(
+                    "breakpoint at {:#05x}: condition {} failed: {e}",
+                    state.pc, cond.source
+                )),
+            },
+            None => {}
+        }
+        for trigger in &mut self.conditions {
+            match trigger.cond.expr.holds(&state, emu).await {
+                Ok(is_true) => {
+                    if is_true && !trigger.was_true {
+                        reasons.push(format!("condition {}", trigger.cond.source));
+                    }
+                    trigger.was_true = is_true;
+                }
+                Err(e) => reasons.push(format!("condition {} failed: {e}", trigger.cond.source)),
+            }
+        }
+        for watch in &mut self.watchpoints {
+            let now = read_watch(watch.target, &state, emu).await?;
+            if now != watch.last {
+                reasons.push(describe_change(watch.target, &watch.last, &now));
+                watch.last = now;
+            }
+        }
+
+        if !reasons.is_empty() {
+            self.paused = true;
+            self.target = None;
+            for reason in reasons {
+                println!("── {reason} ──");
+            }
+        } else if self.paused {
+            println!("── step ──");
+        }
+        println!("{state}");
+        self.print_random(&state, emu).await;
+        if self.paused && state.sp > 0 {
+            println!("{}", CallStack::fetch(emu).await?);
+        }
+        Ok(())
+    }
+
+    async fn print_state<T, P, N>(
+        &self,
+        emu: &Solchip8Instance<T, P, N>,
+        title: &str,
+    ) -> Result<(), alloy::contract::Error>
+    where
+        T: Transport + Clone,
+        P: Provider<T, N>,
+        N: Network,
+    {
+        let state = Chip8State::fetch(emu).await?;
+        println!("── {title} ──");
+        println!("{state}");
+        self.print_random(&state, emu).await;
+        Ok(())
+    }
+
+    /// Print what the `CXNN` at PC will draw if its `tick()` lands in the next block.
+    async fn print_random<T, P, N>(&self, state: &Chip8State, emu: &Solchip8Instance<T, P, N>)
+    where
+        T: Transport + Clone,
+        P: Provider<T, N>,
+        N: Network,
+    {
+        if state.opcode & 0xF000 != 0xC000 {
+            return;
+        }
+        let head = match determinism::head(emu.provider()).await {
+            Ok(head) => head,
+            Err(e) => return println!("random: {e}"),
+        };
+        let timestamp = next_timestamp(&head, self.pinned);
+        let Some((x, value)) = random::predict(state.opcode, state.pc, timestamp, head.hash) else {
+            return;
+        };
+        let guess = if self.pinned { "" } else { ", from the clock" };
+        println!(
+            "random: V{x:X} = {value:02X} in block {} (timestamp {timestamp}{guess})",
+            head.number + 1
+        );
+    }
+}
+
+async fn read_watch<T, P, N>(
+    target: WatchTarget,
+    state: &Chip8State,
+    emu: &Solchip8Instance<T, P, N>,
+) -> Result<Vec<u32>, alloy::contract::Error>
+where
+    T: Transport + Clone,
+    P: Provider<T, N>,
+    N: Network,
+{
+    Ok(match target {
+        WatchTarget::Reg(reg) => vec![reg.read(state)],
+        WatchTarget::Ram(start, end) => {
+            let bytes = storage::read_ram(emu, start as usize, (end - start) as usize).await?;
+            bytes.into_iter().map(u32::from).collect()
+        }
+    })
+}
+
+/// One stack entry: the address `00EE` returns to and the `2NNN` that pushed it.
+#[derive(Debug, Clone, Copy)]
+pub struct Frame {
+    pub return_addr: u16,
+    pub call_site: u16,
+    pub call: u16,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct CallStack {
+    pub sp: u16,
+    /// Outermost call first, as stored in the contract.
+    pub frames: Vec<Frame>,
+}
+
+impl CallStack {
+    pub async fn fetch<T, P, N>(
+        emu: &Solchip8Instance<T, P, N>,
+    ) -> Result<Self, alloy::contract::Error>
+    where
+        T: Transport + Clone,
+        P: Provider<T, N>,
+        N: Network,
+    {
+        let sp = emu.getSP().call().await?._0;
+        let mut frames = Vec::new();
+        for idx in 0..(sp as usize).min(STACK_SIZE) {
+            let return_addr = emu.getStackValue(U256::from(idx)).call().await?._0;
+            // `2NNN` pushes the PC after its own fetch, so the call is the word before.
+            let call_site = return_addr.wrapping_sub(2);
+            let call = read_opcode(emu, call_site).await?;
+            frames.push(Frame {
+                return_addr,
+                call_site,
+                call,
+            });
+        }
+        Ok(CallStack { sp, frames })
+    }
+}
+
+impl fmt::Display for CallStack {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.frames.is_empty() {
+            return write!(f, "call stack: empty (SP {}/{STACK_SIZE})", self.sp);
+        }
+        write!(
+            f,
+            "call stack: SP {}/{STACK_SIZE}, innermost first",
+            self.sp
+        )?;
+        for (idx, frame) in self.frames.iter().enumerate().rev() {
+            write!(
+                f,
+                "\n  #{idx:<2} ret {:#05x}  from {:#05x}  {:04X}  {}",
+                frame.return_addr,
+                frame.call_site,
+                frame.call,
+                disassemble(frame.call)
+            )?;
+        }
+        Ok(())
+    }
+}
+
+/// Timestamp of the block after `head`. Pinned blocks follow exactly; otherwise anvil takes
+/// the wall clock, so this is a guess that can be off by the time the transaction waits.
+fn next_timestamp(head: &Head, pinned: bool) -> u64 {
+    if pinned {
+        return head.timestamp + determinism::INTERVAL;
+    }
+    let now = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |now| now.as_secs());
+    now.max(head.timestamp + 1)
+}
+
+fn describe_change(target: WatchTarget, old: &[u32], new: &[u32]) -> String {
+    match target {
+        WatchTarget::Reg(reg) => format!("{reg} changed: {:#x} -> {:#x}", old[0], new[0]),
+        WatchTarget::Ram(start, _) => {
+            let changes: Vec<_> = old
+                .iter()
+                .zip(new)
+                .enumerate()
+                .filter(|(_, (old, new))| old != new)
+                .map(|(i, (old, new))| {
+                    format!("[{:#05x}] {old:#04x} -> {new:#04x}", start as usize + i)
+                })
+                .collect();
+            format!("{target} changed: {}", changes.join(", "))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn watch_targets() {
+        assert_eq!("V3".parse(), Ok(WatchTarget::Reg(Reg::V(3))));
+        assert_eq!("0x300".parse(), Ok(WatchTarget::Ram(0x300, 0x301)));
+        assert_eq!("300..310".parse(), Ok(WatchTarget::Ram(0x300, 0x310)));
+        assert_eq!("0xfff".parse(), Ok(WatchTarget::Ram(0xfff, 0x1000)));
+        for s in [
+            "0xffff",
+            "0x1000",
+            "0x310..0x300",
+            "0x300..0x300",
+            "0xfff..0x1001",
+        ] {
+            assert!(s.parse::<WatchTarget>().is_err(), "{s}");
+        }
+    }
+
+    #[test]
+    fn break_takes_one_address() {
+        assert_eq!("b 0x23c".parse(), Ok(Command::Break(0x23c, None)));
+        let cond = "V0 > 4".parse().unwrap();
+        assert_eq!(
+            "b 0x23c if V0 > 4".parse(),
+            Ok(Command::Break(0x23c, Some(cond)))
+        );
+        assert_eq!(
+            "b 0x23c junk".parse::<Command>(),
+            Err("unexpected `junk` after the address".to_string())
+        );
+        assert_eq!(
+            "b".parse::<Command>(),
+            Err("`b` needs an address".to_string())
+        );
+        assert!("u 0x200 0x202".parse::<Command>().is_err());
+        assert!("d 0x200 x".parse::<Command>().is_err());
+    }
+}
@@ -0,0 +1,77 @@
+//! Deterministic blocks on a local anvil node, so `CXNN` draws the same numbers every run.
+//!
+//! `CXNN` takes its randomness from `keccak256(block.timestamp, blockhash(block.number - 1),
+//! pc)`. Pinning mines every transaction in its own block as it arrives, with no interval
+//! mining, and spaces block timestamps `INTERVAL` seconds apart from the current head. Two
+//! sessions that start from the same head and send the same transactions then build the same
+//! blocks. Fresh anvil nodes started with the same flags and a fixed `--timestamp` all have
+//! the same head.
+
+use std::fmt;
+
+use alloy::eips::BlockNumberOrTag;
+use alloy::network::{BlockResponse, HeaderResponse, Network};
+use alloy::primitives::BlockHash;
+use alloy::providers::ext::AnvilApi;
+use alloy::providers::Provider;
+use alloy::transports::{Transport, TransportErrorKind, TransportResult};
+
+/// Seconds between pinned blocks.
+pub const INTERVAL: u64 = 1;
+
+/// A block the chain has reached.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Head {
+    pub number: u64,
+    pub hash: BlockHash,
+    pub timestamp: u64,
+}
+
+impl fmt::Display for Head {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "block {} ({}, timestamp {})",
+            self.number, self.hash, self.timestamp
+        )
+    }
+}
+
+/// The latest block.
+pub async fn head<T, P, N>(provider: &P) -> TransportResult<Head>
+where
+    T: Transport + Clone,
+    P: Provider<T, N>,
+    N: Network,
+{
+    let block = provider
+        .get_block_by_number(BlockNumberOrTag::Latest, false)
+        .await?
+        .ok_or_else(|| TransportErrorKind::custom_str("node returned no latest block"))?;
+    let header = block.header();
+    Ok(Head {
+        number: header.number(),
+        hash: header.hash(),
+        timestamp: header.timestamp(),
+    })
+}
+
+/// Mine each transaction in its own block at pinned timestamps from now on. Returns the head
+/// the pinned blocks build on.
+pub async fn pin<T, P, N>(provider: &P) -> TransportResult<Head>
+where
+    T: Transport + Clone,
+    P: Provider<T, N>,
+    N: Network,
+{
+    provider.anvil_set_interval_mining(0).await?;
+    provider.anvil_set_auto_mine(true).await?;
+    let head = head(provider).await?;
+    provider
+        .anvil_set_next_block_timestamp(head.timestamp + INTERVAL)
+        .await?;
+    provider
+        .anvil_set_block_timestamp_interval(INTERVAL)
+        .await?;
+    Ok(head)
+}
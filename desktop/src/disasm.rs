@@ -0,0 +1,60 @@
+//! CHIP-8 disassembler, using the mnemonics from the opcode table in the top-level README.
+
+/// Disassemble a single opcode.
+pub fn disassemble(op: u16) -> String {
+    let x = (op & 0x0F00) >> 8;
+    let y = (op & 0x00F0) >> 4;
+    let n = op & 0x000F;
+    let nn = op & 0x00FF;
+    let nnn = op & 0x0FFF;
+
+    match (op & 0xF000) >> 12 {
+        0x0 if op == 0x0000 => "NOP".to_string(),
+        0x0 if op == 0x00E0 => "CLS".to_string(),
+        0x0 if op == 0x00EE => "RET".to_string(),
+        0x1 => format!("JP {nnn:#05x}"),
+        0x2 => format!("CALL {nnn:#05x}"),
+        0x3 => format!("SE V{x:X}, {nn:#04x}"),
+        0x4 => format!("SNE V{x:X}, {nn:#04x}"),
+        0x5 if n == 0x0 => format!("SE V{x:X}, V{y:X}"),
+        0x6 => format!("LD V{x:X}, {nn:#04x}"),
+        0x7 => format!("ADD V{x:X}, {nn:#04x}"),
+        0x8 => match n {
+            0x0 => format!("LD V{x:X}, V{y:X}"),
+            0x1 => format!("OR V{x:X}, V{y:X}"),
+            0x2 => format!("AND V{x:X}, V{y:X}"),
+            0x3 => format!("XOR V{x:X}, V{y:X}"),
+            0x4 => format!("ADD V{x:X}, V{y:X}"),
+            0x5 => format!("SUB V{x:X}, V{y:X}"),
+            0x6 => format!("SHR V{x:X}"),
+            0x7 => format!("SUBN V{x:X}, V{y:X}"),
+            0xE => format!("SHL V{x:X}"),
+            _ => unknown(op),
+        },
+        0x9 if n == 0x0 => format!("SNE V{x:X}, V{y:X}"),
+        0xA => format!("LD I, {nnn:#05x}"),
+        0xB => format!("JP V0, {nnn:#05x}"),
+        0xC => format!("RND V{x:X}, {nn:#04x}"),
+        0xD => format!("DRW V{x:X}, V{y:X}, {n}"),
+        0xE if nn == 0x9E => format!("SKP V{x:X}"),
+        0xE if nn == 0xA1 => format!("SKNP V{x:X}"),
+        0xF => match nn {
+            0x07 => format!("LD V{x:X}, DT"),
+            0x0A => format!("LD V{x:X}, K"),
+            0x15 => format!("LD DT, V{x:X}"),
+            0x18 => format!("LD ST, V{x:X}"),
+            0x1E => format!("ADD I, V{x:X}"),
+            0x29 => format!("LD F, V{x:X}"),
+            0x33 => format!("LD B, V{x:X}"),
+            0x55 => format!("LD [I], V{x:X}"),
+            0x65 => format!("LD V{x:X}, [I]"),
+            _ => unknown(op),
+        },
+        _ => unknown(op),
+    }
+}
+
+/// Opcodes the contract reverts on with "Opcode not implemented".
+fn unknown(op: u16) -> String {
+    format!("DW {op:#06x}")
+}
@@ -0,0 +1,129 @@
+//! Decoding of the contract's `uint256[8]` framebuffer.
+
+use alloy::eips::BlockId;
+use alloy::network::Network;
+use alloy::primitives::U256;
+use alloy::providers::Provider;
+use alloy::pubsub::Subscription;
+use alloy::rpc::types::Header;
+use alloy::transports::Transport;
+use bitvec::prelude::*;
+use tokio::sync::broadcast::error::TryRecvError;
+
+use crate::Solchip8::Solchip8Instance;
+use crate::{SCREEN_HEIGHT, SCREEN_WIDTH};
+
+/// Number of pixels on the screen.
+pub const PIXELS: usize = SCREEN_WIDTH * SCREEN_HEIGHT;
+
+/// Unpack `getDisplay()` into one flag per pixel, row by row from the top left.
+///
+/// Each word holds four rows, most significant bit first, as `DXYN` writes them.
+pub fn decode(screen: &[U256]) -> [bool; PIXELS] {
+    let mut pixels = [false; PIXELS];
+    for (i, word) in screen.iter().enumerate() {
+        let bytes: [u8; 32] = word.to_be_bytes();
+        for (j, bit) in bytes.view_bits::<Msb0>().iter().enumerate() {
+            if let Some(pixel) = pixels.get_mut(i * 256 + j) {
+                *pixel = *bit;
+            }
+        }
+    }
+    pixels
+}
+
+/// Plain (`P1`) PBM image of the screen, one character per pixel.
+pub fn to_pbm(pixels: &[bool; PIXELS]) -> String {
+    let mut out = format!("P1\n{SCREEN_WIDTH} {SCREEN_HEIGHT}\n");
+    for row in pixels.chunks(SCREEN_WIDTH) {
+        out.extend(row.iter().map(|&on| if on { '1' } else { '0' }));
+        out.push('\n');
+    }
+    out
+}
+
+/// `getDisplay()` read at most once per block.
+///
+/// The screen only changes when a transaction is mined, so it is read again only once a new
+/// block arrives, at that block. Over WebSocket and IPC new blocks come from a `newHeads`
+/// subscription and a frame without one costs no request; over HTTP every frame asks for the
+/// block number.
+#[derive(Debug)]
+pub struct Screen {
+    /// Block the words were read at.
+    block: Option<u64>,
+    words: [U256; 8],
+    /// `newHeads`, if the transport supports subscriptions.
+    heads: Option<Subscription<Header>>,
+}
+
+impl Screen {
+    /// Subscribe to `newHeads` where the transport allows it, polling the block number if not.
+    pub async fn new<T, P, N>(emu: &Solchip8Instance<T, P, N>) -> Self
+    where
+        T: Transport + Clone,
+        P: Provider<T, N>,
+        N: Network,
+    {
+        Self {
+            block: None,
+            words: Default::default(),
+            heads: emu.provider().subscribe(("newHeads",)).await.ok(),
+        }
+    }
+
+    /// Read the screen again if a block was mined since the last read. Returns whether it did.
+    pub async fn refresh<T, P, N>(
+        &mut self,
+        emu: &Solchip8Instance<T, P, N>,
+    ) -> Result<bool, alloy::contract::Error>
+    where
+        T: Transport + Clone,
+        P: Provider<T, N>,
+        N: Network,
+    {
+        let block = match self.latest_head() {
+            Some(block) => block,
+            None => emu.provider().get_block_number().await?,
+        };
+        if self.block == Some(block) {
+            return Ok(false);
+        }
+        self.words = emu
+            .getDisplay()
+            .block(BlockId::number(block))
+            .call()
+            .await?
+            ._0;
+        self.block = Some(block);
+        Ok(true)
+    }
+
+    /// Number of the newest head received, or of the block last read if none came since.
+    /// `None` when polling, before anything was read, or once the subscription closed.
+    fn latest_head(&mut self) -> Option<u64> {
+        let heads = self.heads.as_mut()?;
+        let mut latest = self.block;
+        loop {
+            match heads.try_recv() {
+                Ok(header) => latest = Some(header.number),
+                Err(TryRecvError::Empty) => return latest,
+                // Skipped heads are older than the ones still queued
+                Err(TryRecvError::Lagged(_)) => {}
+                Err(TryRecvError::Closed) => {
+                    self.heads = None;
+                    return None;
+                }
+            }
+        }
+    }
+
+    /// Words as last read, all zero before the first read.
+    pub fn words(&self) -> &[U256; 8] {
+        &self.words
+    }
+
+    pub fn pixels(&self) -> [bool; PIXELS] {
+        decode(&self.words)
+    }
+}
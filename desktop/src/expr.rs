@@ -0,0 +1,459 @@
+//! Small expression language for conditional breakpoints, e.g. `V3 == 0x10 && I > 0x300`.
+//!
+//! Operands are numbers (decimal or `0x` hex), registers (`V0`-`VF`, `I`, `PC`, `SP`, `DT`,
+//! `ST`, `KEYS`, `OP` for the opcode at PC) and RAM bytes (`[I + 2]`). Operators follow C
+//! precedence: `! ~ -`, `* / %`, `+ -`, `<< >>`, `< <= > >=`, `== !=`, `&`, `^`, `|`, `&&`, `||`.
+//! Comparisons and logical operators yield 0 or 1.
+
+use std::collections::BTreeMap;
+use std::fmt;
+use std::str::FromStr;
+
+use alloy::network::Network;
+use alloy::primitives::U256;
+use alloy::providers::Provider;
+use alloy::transports::Transport;
+
+use crate::state::Chip8State;
+use crate::Solchip8::Solchip8Instance;
+
+/// A register that can be named in expressions and watchpoints.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Reg {
+    V(u8),
+    I,
+    Pc,
+    Sp,
+    Dt,
+    St,
+    Keys,
+    Op,
+}
+
+impl Reg {
+    pub fn read(&self, state: &Chip8State) -> u32 {
+        match self {
+            Reg::V(x) => state.v[*x as usize] as u32,
+            Reg::I => state.i as u32,
+            Reg::Pc => state.pc as u32,
+            Reg::Sp => state.sp as u32,
+            Reg::Dt => state.dt as u32,
+            Reg::St => state.st as u32,
+            Reg::Keys => state.keys as u32,
+            Reg::Op => state.opcode as u32,
+        }
+    }
+}
+
+impl FromStr for Reg {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let upper = s.to_ascii_uppercase();
+        match upper.as_str() {
+            "I" => Ok(Reg::I),
+            "PC" => Ok(Reg::Pc),
+            "SP" => Ok(Reg::Sp),
+            "DT" => Ok(Reg::Dt),
+            "ST" => Ok(Reg::St),
+            "KEYS" => Ok(Reg::Keys),
+            "OP" => Ok(Reg::Op),
+            _ => match upper.strip_prefix('V') {
+                Some(x) if x.len() == 1 => u8::from_str_radix(x, 16)
+                    .map(Reg::V)
+                    .map_err(|_| format!("unknown register `{s}`")),
+                _ => Err(format!("unknown register `{s}`")),
+            },
+        }
+    }
+}
+
+impl fmt::Display for Reg {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Reg::V(x) => write!(f, "V{x:X}"),
+            Reg::I => write!(f, "I"),
+            Reg::Pc => write!(f, "PC"),
+            Reg::Sp => write!(f, "SP"),
+            Reg::Dt => write!(f, "DT"),
+            Reg::St => write!(f, "ST"),
+            Reg::Keys => write!(f, "KEYS"),
+            Reg::Op => write!(f, "OP"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum UnOp {
+    Not,
+    BitNot,
+    Neg,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BinOp {
+    Or,
+    And,
+    BitOr,
+    BitXor,
+    BitAnd,
+    Eq,
+    Ne,
+    Lt,
+    Le,
+    Gt,
+    Ge,
+    Shl,
+    Shr,
+    Add,
+    Sub,
+    Mul,
+    Div,
+    Rem,
+}
+
+impl BinOp {
+    /// Binding power, higher binds tighter.
+    fn precedence(&self) -> u8 {
+        match self {
+            BinOp::Or => 1,
+            BinOp::And => 2,
+            BinOp::BitOr => 3,
+            BinOp::BitXor => 4,
+            BinOp::BitAnd => 5,
+            BinOp::Eq | BinOp::Ne => 6,
+            BinOp::Lt | BinOp::Le | BinOp::Gt | BinOp::Ge => 7,
+            BinOp::Shl | BinOp::Shr => 8,
+            BinOp::Add | BinOp::Sub => 9,
+            BinOp::Mul | BinOp::Div | BinOp::Rem => 10,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Expr {
+    Num(u32),
+    Reg(Reg),
+    Ram(Box<Expr>),
+    Unary(UnOp, Box<Expr>),
+    Binary(BinOp, Box<Expr>, Box<Expr>),
+}
+
+impl Expr {
+    /// Evaluate against a state snapshot.
+    ///
+    /// RAM bytes are looked up in `ram`; a byte that is not there yet is returned as `Err(addr)`
+    /// so the caller can fetch it from the contract and evaluate again.
+    pub fn eval(&self, state: &Chip8State, ram: &BTreeMap<u16, u8>) -> Result<u32, u16> {
+        Ok(match self {
+            Expr::Num(n) => *n,
+            Expr::Reg(reg) => reg.read(state),
+            Expr::Ram(addr) => {
+                let addr = (addr.eval(state, ram)? & 0xFFF) as u16;
+                *ram.get(&addr).ok_or(addr)? as u32
+            }
+            Expr::Unary(op, e) => {
+                let v = e.eval(state, ram)?;
+                match op {
+                    UnOp::Not => (v == 0) as u32,
+                    UnOp::BitNot => !v,
+                    UnOp::Neg => v.wrapping_neg(),
+                }
+            }
+            Expr::Binary(BinOp::And, l, r) => {
+                (l.eval(state, ram)? != 0 && r.eval(state, ram)? != 0) as u32
+            }
+            Expr::Binary(BinOp::Or, l, r) => {
+                (l.eval(state, ram)? != 0 || r.eval(state, ram)? != 0) as u32
+            }
+            Expr::Binary(op, l, r) => {
+                let (l, r) = (l.eval(state, ram)?, r.eval(state, ram)?);
+                match op {
+                    BinOp::BitOr => l | r,
+                    BinOp::BitXor => l ^ r,
+                    BinOp::BitAnd => l & r,
+                    BinOp::Eq => (l == r) as u32,
+                    BinOp::Ne => (l != r) as u32,
+                    BinOp::Lt => (l < r) as u32,
+                    BinOp::Le => (l <= r) as u32,
+                    BinOp::Gt => (l > r) as u32,
+                    BinOp::Ge => (l >= r) as u32,
+                    BinOp::Shl => l.checked_shl(r).unwrap_or(0),
+                    BinOp::Shr => l.checked_shr(r).unwrap_or(0),
+                    BinOp::Add => l.wrapping_add(r),
+                    BinOp::Sub => l.wrapping_sub(r),
+                    BinOp::Mul => l.wrapping_mul(r),
+                    BinOp::Div => l.checked_div(r).unwrap_or(0),
+                    BinOp::Rem => l.checked_rem(r).unwrap_or(0),
+                    BinOp::And | BinOp::Or => unreachable!(),
+                }
+            }
+        })
+    }
+
+    /// Evaluate as a condition, fetching the RAM bytes it reads from the contract as needed.
+    pub async fn holds<T, P, N>(
+        &self,
+        state: &Chip8State,
+        emu: &Solchip8Instance<T, P, N>,
+    ) -> Result<bool, alloy::contract::Error>
+    where
+        T: Transport + Clone,
+        P: Provider<T, N>,
+        N: Network,
+    {
+        let mut ram = BTreeMap::new();
+        loop {
+            match self.eval(state, &ram) {
+                Ok(v) => return Ok(v != 0),
+                Err(addr) => {
+                    let value = emu.getRAMValueAt(U256::from(addr)).call().await?._0;
+                    ram.insert(addr, value);
+                }
+            }
+        }
+    }
+}
+
+impl FromStr for Expr {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut parser = Parser {
+            tokens: tokenize(s)?,
+            pos: 0,
+        };
+        let expr = parser.expr(0)?;
+        match parser.peek() {
+            None => Ok(expr),
+            Some(token) => Err(format!("unexpected `{token}`")),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Token {
+    Num(u32),
+    Ident(String),
+    Op(&'static str),
+}
+
+impl fmt::Display for Token {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Token::Num(n) => write!(f, "{n}"),
+            Token::Ident(s) => write!(f, "{s}"),
+            Token::Op(op) => write!(f, "{op}"),
+        }
+    }
+}
+
+/// Operators, longest first so `<=` wins over `<`.
+const OPERATORS: &[&str] = &[
+    "&&", "||", "==", "!=", "<=", ">=", "<<", ">>", "<", ">", "+", "-", "*", "/", "%", "&", "|",
+    "^", "!", "~", "(", ")", "[", "]",
+];
+
+fn tokenize(s: &str) -> Result<Vec<Token>, String> {
+    let mut tokens = Vec::new();
+    let mut rest = s.trim_start();
+    while let Some(c) = rest.chars().next() {
+        if c.is_ascii_alphanumeric() || c == '_' {
+            let end = rest
+                .find(|c: char| !(c.is_ascii_alphanumeric() || c == '_'))
+                .unwrap_or(rest.len());
+            let word = &rest[..end];
+            let token = if c.is_ascii_digit() {
+                let n = match word.strip_prefix("0x").or(word.strip_prefix("0X")) {
+                    Some(hex) => u32::from_str_radix(hex, 16),
+                    None => word.parse(),
+                };
+                Token::Num(n.map_err(|_| format!("invalid number `{word}`"))?)
+            } else {
+                Token::Ident(word.to_string())
+            };
+            tokens.push(token);
+            rest = &rest[end..];
+        } else {
+            let op = OPERATORS
+                .iter()
+                .find(|op| rest.starts_with(**op))
+                .ok_or(format!("unexpected character `{c}`"))?;
+            tokens.push(Token::Op(op));
+            rest = &rest[op.len()..];
+        }
+        rest = rest.trim_start();
+    }
+    Ok(tokens)
+}
+
+struct Parser {
+    tokens: Vec<Token>,
+    pos: usize,
+}
+
+impl Parser {
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.pos)
+    }
+
+    fn next(&mut self) -> Option<Token> {
+        let token = self.tokens.get(self.pos).cloned();
+        self.pos += 1;
+        token
+    }
+
+    fn expect(&mut self, op: &str) -> Result<(), String> {
+        match self.next() {
+            Some(Token::Op(o)) if o == op => Ok(()),
+            Some(token) => Err(format!("expected `{op}`, found `{token}`")),
+            None => Err(format!("expected `{op}`")),
+        }
+    }
+
+    /// Precedence climbing: parse operators that bind tighter than `min`.
+    fn expr(&mut self, min: u8) -> Result<Expr, String> {
+        let mut lhs = self.unary()?;
+        while let Some(op) = self.peek().and_then(binop) {
+            if op.precedence() <= min {
+                break;
+            }
+            self.pos += 1;
+            let rhs = self.expr(op.precedence())?;
+            lhs = Expr::Binary(op, Box::new(lhs), Box::new(rhs));
+        }
+        Ok(lhs)
+    }
+
+    fn unary(&mut self) -> Result<Expr, String> {
+        match self.next() {
+            Some(Token::Num(n)) => Ok(Expr::Num(n)),
+            Some(Token::Ident(name)) => Ok(Expr::Reg(name.parse()?)),
+            Some(Token::Op("!")) => Ok(Expr::Unary(UnOp::Not, Box::new(self.unary()?))),
+            Some(Token::Op("~")) => Ok(Expr::Unary(UnOp::BitNot, Box::new(self.unary()?))),
+            Some(Token::Op("-")) => Ok(Expr::Unary(UnOp::Neg, Box::new(self.unary()?))),
+            Some(Token::Op("(")) => {
+                let e = self.expr(0)?;
+                self.expect(")")?;
+                Ok(e)
+            }
+            Some(Token::Op("[")) => {
+                let e = self.expr(0)?;
+                self.expect("]")?;
+                Ok(Expr::Ram(Box::new(e)))
+            }
+            Some(token) => Err(format!("unexpected `{token}`")),
+            None => Err("unexpected end of expression".to_string()),
+        }
+    }
+}
+
+fn binop(token: &Token) -> Option<BinOp> {
+    let Token::Op(op) = token else { return None };
+    Some(match *op {
+        "||" => BinOp::Or,
+        "&&" => BinOp::And,
+        "|" => BinOp::BitOr,
+        "^" => BinOp::BitXor,
+        "&" => BinOp::BitAnd,
+        "==" => BinOp::Eq,
+        "!=" => BinOp::Ne,
+        "<" => BinOp::Lt,
+        "<=" => BinOp::Le,
+        ">" => BinOp::Gt,
+        ">=" => BinOp::Ge,
+        "<<" => BinOp::Shl,
+        ">>" => BinOp::Shr,
+        "+" => BinOp::Add,
+        "-" => BinOp::Sub,
+        "*" => BinOp::Mul,
+        "/" => BinOp::Div,
+        "%" => BinOp::Rem,
+        _ => return None,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn eval(s: &str) -> u32 {
+        let state = Chip8State {
+            pc: 0x2f0,
+            i: 0x300,
+            v: [0, 1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12, 13, 14, 15],
+            ..Default::default()
+        };
+        let ram = BTreeMap::from([(0x300, 0xAB), (0x302, 0x12)]);
+        s.parse::<Expr>().unwrap().eval(&state, &ram).unwrap()
+    }
+
+    fn error(s: &str) -> String {
+        s.parse::<Expr>().unwrap_err()
+    }
+
+    #[test]
+    fn precedence() {
+        assert_eq!(eval("1 + 2 * 3"), 7);
+        assert_eq!(eval("10 - 4 - 3"), 3);
+        assert_eq!(eval("100 / 10 / 5"), 2);
+        assert_eq!(eval("1 << 2 + 1"), 8);
+        assert_eq!(eval("1 + 1 == 2"), 1);
+        assert_eq!(eval("2 < 3 == 1"), 1);
+        assert_eq!(eval("6 & 3 == 3"), 0);
+        assert_eq!(eval("1 | 2 ^ 3 & 1"), 3);
+        assert_eq!(eval("0 && 1 || 1"), 1);
+        assert_eq!(eval("1 || 0 && 0"), 1);
+        assert_eq!(eval("V3 == 3 && I > 0x2ff"), 1);
+    }
+
+    #[test]
+    fn unary() {
+        assert_eq!(eval("!0"), 1);
+        assert_eq!(eval("!5"), 0);
+        assert_eq!(eval("~0"), u32::MAX);
+        assert_eq!(eval("-1"), u32::MAX);
+        assert_eq!(eval("- -2"), 2);
+        assert_eq!(eval("!V0 + 1"), 2);
+        assert_eq!(eval("-2 * 3"), (-6i32) as u32);
+    }
+
+    #[test]
+    fn parentheses_and_ram() {
+        assert_eq!(eval("(1 + 2) * 3"), 9);
+        assert_eq!(eval("((4))"), 4);
+        assert_eq!(eval("[I]"), 0xAB);
+        assert_eq!(eval("[I + 2] == 0x12"), 1);
+        assert_eq!(eval("[I + (1 + 1)]"), 0x12);
+    }
+
+    #[test]
+    fn registers_and_numbers() {
+        assert_eq!(eval("VF + vA"), 25);
+        assert_eq!(eval("PC"), 0x2f0);
+        assert_eq!(eval("0X10 + 0x10 + 10"), 42);
+    }
+
+    #[test]
+    fn missing_ram_byte_is_reported() {
+        let expr: Expr = "[I + 1]".parse().unwrap();
+        let state = Chip8State {
+            i: 0x300,
+            ..Default::default()
+        };
+        assert_eq!(expr.eval(&state, &BTreeMap::new()), Err(0x301));
+    }
+
+    #[test]
+    fn errors() {
+        assert_eq!(error("1 +"), "unexpected end of expression");
+        assert_eq!(error("(1 + 2"), "expected `)`");
+        assert_eq!(error("[I"), "expected `]`");
+        assert_eq!(error("(1 2)"), "expected `)`, found `2`");
+        assert_eq!(error("1 2"), "unexpected `2`");
+        assert_eq!(error("VG"), "unknown register `VG`");
+        assert_eq!(error("0xZZ"), "invalid number `0xZZ`");
+        assert_eq!(error("V0 $ 1"), "unexpected character `$`");
+        assert_eq!(error("* 2"), "unexpected `*`");
+    }
+}
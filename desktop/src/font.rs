@@ -0,0 +1,188 @@
+//! Alternative 4x5 hex fonts, written over the contract's `FONTSET` copy in RAM 0x000-0x04F.
+//!
+//! A font file lists the 80 bytes in hex, five rows per digit from 0 to F. Bytes may be
+//! separated by whitespace or commas, take an optional `0x` prefix, and `#` starts a comment.
+//! The variants in `fonts/` are built in and can be selected by name.
+
+use std::fmt;
+use std::fs;
+use std::io;
+
+use alloy::network::Network;
+use alloy::primitives::U256;
+use alloy::providers::Provider;
+use alloy::transports::Transport;
+use desktop::client;
+
+use crate::rom::FONTSET_SIZE;
+use crate::storage;
+use crate::Solchip8::Solchip8Instance;
+
+/// Fonts shipped in `fonts/`, by name.
+pub const BUILTIN: &[(&str, &str)] = &[
+    ("chip8", include_str!("../fonts/chip8.txt")),
+    ("vip", include_str!("../fonts/vip.txt")),
+    ("dream6800", include_str!("../fonts/dream6800.txt")),
+    ("eti660", include_str!("../fonts/eti660.txt")),
+];
+
+#[derive(Debug)]
+pub enum FontError {
+    Io(io::Error),
+    Byte(String),
+    Length(usize),
+    /// A row of `digit` sets bits outside the 4 pixel wide glyph.
+    TooWide {
+        digit: usize,
+        row: usize,
+    },
+}
+
+impl fmt::Display for FontError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            FontError::Io(e) => write!(f, "{e}"),
+            FontError::Byte(token) => write!(f, "`{token}` is not a hex byte"),
+            FontError::Length(len) => {
+                write!(
+                    f,
+                    "expected {FONTSET_SIZE} bytes (16 digits x 5 rows), found {len}"
+                )
+            }
+            FontError::TooWide { digit, row } => {
+                write!(f, "digit {digit:X} row {row} is wider than 4 pixels")
+            }
+        }
+    }
+}
+
+impl std::error::Error for FontError {}
+
+impl From<io::Error> for FontError {
+    fn from(e: io::Error) -> Self {
+        FontError::Io(e)
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Font {
+    pub name: String,
+    pub bytes: [u8; FONTSET_SIZE],
+}
+
+impl Font {
+    /// A built-in font by name, or else a font file at `spec`.
+    pub fn load(spec: &str) -> Result<Self, FontError> {
+        if let Some(font) = Font::builtin(spec) {
+            return Ok(font);
+        }
+        Font::parse(spec, &fs::read_to_string(spec)?)
+    }
+
+    pub fn builtin(name: &str) -> Option<Self> {
+        let (name, source) = BUILTIN.iter().find(|(builtin, _)| *builtin == name)?;
+        Some(Font::parse(name, source).expect("built-in fonts are valid"))
+    }
+
+    pub fn parse(name: &str, source: &str) -> Result<Self, FontError> {
+        let mut bytes = Vec::with_capacity(FONTSET_SIZE);
+        for line in source.lines() {
+            let line = line.split('#').next().unwrap_or_default();
+            for token in line.split(|c: char| c.is_whitespace() || c == ',') {
+                if token.is_empty() {
+                    continue;
+                }
+                let digits = token
+                    .strip_prefix("0x")
+                    .or_else(|| token.strip_prefix("0X"))
+                    .unwrap_or(token);
+                let byte = u8::from_str_radix(digits, 16)
+                    .map_err(|_| FontError::Byte(token.to_string()))?;
+                bytes.push(byte);
+            }
+        }
+        let bytes: [u8; FONTSET_SIZE] = bytes
+            .try_into()
+            .map_err(|bytes: Vec<u8>| FontError::Length(bytes.len()))?;
+        if let Some(idx) = bytes.iter().position(|byte| byte & 0x0F != 0) {
+            return Err(FontError::TooWide {
+                digit: idx / 5,
+                row: idx % 5,
+            });
+        }
+        Ok(Font {
+            name: name.to_string(),
+            bytes,
+        })
+    }
+
+    /// Write the font into RAM 0x000-0x04F, skipping bytes that already match.
+    pub async fn install<T, P, N>(
+        &self,
+        emu: &Solchip8Instance<T, P, N>,
+    ) -> Result<(), alloy::contract::Error>
+    where
+        T: Transport + Clone,
+        P: Provider<T, N>,
+        N: Network,
+    {
+        let current = storage::read_ram(emu, 0, FONTSET_SIZE).await?;
+        let mut writes = 0;
+        for (addr, (&byte, &old)) in self.bytes.iter().zip(&current).enumerate() {
+            if byte != old {
+                client::transact(emu.setRAMValueAt(U256::from(addr), byte)).await?;
+                writes += 1;
+            }
+        }
+        println!("font {} installed, {} bytes written", self.name, writes);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn builtin_fonts_parse() {
+        for (name, _) in BUILTIN {
+            let font = Font::builtin(name).unwrap();
+            assert_eq!(font.name, *name);
+        }
+        assert!(Font::builtin("comic").is_none());
+    }
+
+    #[test]
+    fn bytes_take_an_optional_prefix_and_comments() {
+        let source = "# zero\n0xF0, 0X90 90\n".to_string() + &" 00".repeat(FONTSET_SIZE - 3);
+        let font = Font::parse("test", &source).unwrap();
+        assert_eq!(font.bytes[..4], [0xF0, 0x90, 0x90, 0x00]);
+    }
+
+    #[test]
+    fn invalid_fonts_are_rejected() {
+        assert!(matches!(
+            Font::parse("test", "F0 0xZZ"),
+            Err(FontError::Byte(token)) if token == "0xZZ"
+        ));
+        // A lone prefix is not a byte
+        assert!(matches!(
+            Font::parse("test", "0x"),
+            Err(FontError::Byte(token)) if token == "0x"
+        ));
+        assert!(matches!(
+            Font::parse("test", &"F0 ".repeat(FONTSET_SIZE - 1)),
+            Err(FontError::Length(79))
+        ));
+        assert!(matches!(
+            Font::parse("test", &"F0 ".repeat(FONTSET_SIZE + 1)),
+            Err(FontError::Length(81))
+        ));
+        let mut source = "F0 ".repeat(FONTSET_SIZE - 1);
+        source.insert_str(3 * 12, "F8 ");
+        assert!(matches!(
+            Font::parse("test", &source),
+            Err(FontError::TooWide { digit: 2, row: 2 })
+        ));
+    }
+}
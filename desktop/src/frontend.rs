@@ -0,0 +1,11 @@
+//! Helpers shared by the SDL and terminal frontends.
+
+use std::path::PathBuf;
+
+/// First `screenshot-NNN.png` in the working directory that does not exist yet.
+pub fn next_path() -> PathBuf {
+    (0..)
+        .map(|n| PathBuf::from(format!("screenshot-{n:03}.png")))
+        .find(|path| !path.exists())
+        .unwrap()
+}
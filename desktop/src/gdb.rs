@@ -0,0 +1,683 @@
+//! GDB Remote Serial Protocol stub, so a GDB client can drive the emulator inside the EVM.
+//!
+//! Registers are numbered as in `TARGET_XML` and sent little-endian: 0-15 `V0`-`VF`,
+//! 16 `I`, 17 `PC`, 18 `SP`, 19 `DT`, 20 `ST`. Memory is the contract's 4 KiB of RAM,
+//! read from storage and written with `setRAMValueAt`. `s` sends one `tick()`, `c` keeps
+//! ticking until a PC breakpoint (`Z0`/`Z1`) is hit or the client interrupts.
+
+use std::collections::BTreeSet;
+use std::io;
+
+use alloy::network::Network;
+use alloy::primitives::U256;
+use alloy::providers::Provider;
+use alloy::transports::Transport;
+use desktop::client::transact;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::{TcpListener, TcpStream};
+
+use crate::rom::RAM_SIZE;
+use crate::state::{Chip8State, NUM_REGS};
+use crate::storage::read_ram;
+use crate::Solchip8::Solchip8Instance;
+
+const TARGET_XML: &str = r#"<?xml version="1.0"?>
+<!DOCTYPE target SYSTEM "gdb-target.dtd">
+<target version="1.0">
+  <feature name="org.solchip8.chip8">
+    <reg name="v0" bitsize="8" type="uint8" regnum="0"/>
+    <reg name="v1" bitsize="8" type="uint8"/>
+    <reg name="v2" bitsize="8" type="uint8"/>
+    <reg name="v3" bitsize="8" type="uint8"/>
+    <reg name="v4" bitsize="8" type="uint8"/>
+    <reg name="v5" bitsize="8" type="uint8"/>
+    <reg name="v6" bitsize="8" type="uint8"/>
+    <reg name="v7" bitsize="8" type="uint8"/>
+    <reg name="v8" bitsize="8" type="uint8"/>
+    <reg name="v9" bitsize="8" type="uint8"/>
+    <reg name="va" bitsize="8" type="uint8"/>
+    <reg name="vb" bitsize="8" type="uint8"/>
+    <reg name="vc" bitsize="8" type="uint8"/>
+    <reg name="vd" bitsize="8" type="uint8"/>
+    <reg name="ve" bitsize="8" type="uint8"/>
+    <reg name="vf" bitsize="8" type="uint8"/>
+    <reg name="i" bitsize="16" type="data_ptr"/>
+    <reg name="pc" bitsize="16" type="code_ptr"/>
+    <reg name="sp" bitsize="16" type="uint16"/>
+    <reg name="dt" bitsize="8" type="uint8"/>
+    <reg name="st" bitsize="8" type="uint8"/>
+  </feature>
+</target>
+"#;
+
+const REG_I: usize = 16;
+const REG_PC: usize = 17;
+const REG_SP: usize = 18;
+const REG_DT: usize = 19;
+const REG_ST: usize = 20;
+
+/// SIGTRAP, reported for steps and breakpoints.
+const SIGTRAP: &str = "S05";
+/// SIGILL, reported when `tick()` reverts.
+const SIGILL: &str = "S04";
+/// SIGINT, reported when the client interrupts a continue.
+const SIGINT: &str = "S02";
+
+/// Serve GDB clients on `listen`, one at a time, against the same contract.
+pub async fn serve<T, P, N>(emu: &Solchip8Instance<T, P, N>, listen: &str) -> io::Result<()>
+where
+    T: Transport + Clone,
+    P: Provider<T, N>,
+    N: Network,
+{
+    let listener = TcpListener::bind(listen).await?;
+    println!("GDB stub listening on {}", listener.local_addr()?);
+    loop {
+        let (stream, peer) = listener.accept().await?;
+        println!("GDB client connected from {peer}");
+        let mut session = Session {
+            emu,
+            stream,
+            buf: Vec::new(),
+            breakpoints: BTreeSet::new(),
+            no_ack: false,
+        };
+        match session.run().await {
+            Ok(()) => println!("GDB client disconnected"),
+            Err(e) => println!("GDB session ended: {e}"),
+        }
+    }
+}
+
+enum Packet {
+    Data(String),
+    Interrupt,
+}
+
+struct Session<'a, T, P, N> {
+    emu: &'a Solchip8Instance<T, P, N>,
+    stream: TcpStream,
+    /// Bytes received but not parsed yet.
+    buf: Vec<u8>,
+    breakpoints: BTreeSet<u16>,
+    no_ack: bool,
+}
+
+impl<T, P, N> Session<'_, T, P, N>
+where
+    T: Transport + Clone,
+    P: Provider<T, N>,
+    N: Network,
+{
+    async fn run(&mut self) -> io::Result<()> {
+        while let Some(packet) = self.read_packet().await? {
+            let Packet::Data(data) = packet else {
+                // Nothing is running, so there is nothing to interrupt
+                self.write_packet(SIGINT).await?;
+                continue;
+            };
+            if data == "k" {
+                return Ok(());
+            }
+            if data.is_empty() {
+                self.write_packet("").await?;
+                continue;
+            }
+            let reply = self.handle(&data).await?;
+            self.write_packet(&reply).await?;
+            if data == "D" {
+                return Ok(());
+            }
+        }
+        Ok(())
+    }
+
+    async fn handle(&mut self, data: &str) -> io::Result<String> {
+        // Split after the first character, which need not be ASCII in a malformed packet
+        let (cmd, args) = data.split_at(data.chars().next().map_or(0, char::len_utf8));
+        let reply = match cmd {
+            "?" => SIGTRAP.to_string(),
+            "q" => self.query(args),
+            "Q" if args == "StartNoAckMode" => {
+                self.no_ack = true;
+                "OK".to_string()
+            }
+            "H" | "D" => "OK".to_string(),
+            "g" => match Chip8State::fetch(self.emu).await {
+                Ok(state) => encode_registers(&state),
+                Err(e) => error(e),
+            },
+            "G" => self.write_registers(args).await,
+            "p" => match usize::from_str_radix(args, 16) {
+                Ok(n) => self.read_register(n).await,
+                Err(_) => "E01".to_string(),
+            },
+            "P" => match args.split_once('=') {
+                Some((n, value)) => match usize::from_str_radix(n, 16) {
+                    Ok(n) => self.write_register(n, &decode_hex(value)).await,
+                    Err(_) => "E01".to_string(),
+                },
+                None => "E01".to_string(),
+            },
+            "m" => self.read_memory(args).await,
+            "M" => self.write_memory(args).await,
+            "Z" | "z" => self.breakpoint(cmd == "Z", args),
+            "s" => self.step().await,
+            "c" => self.resume().await?,
+            "v" if args == "Cont?" => "vCont;c;s".to_string(),
+            "v" if args.starts_with("Cont;s") => self.step().await,
+            "v" if args.starts_with("Cont;c") => self.resume().await?,
+            _ => String::new(),
+        };
+        Ok(reply)
+    }
+
+    fn query(&self, args: &str) -> String {
+        if args.starts_with("Supported") {
+            "PacketSize=1000;qXfer:features:read+;QStartNoAckMode+;swbreak+;hwbreak+".to_string()
+        } else if let Some(range) = args.strip_prefix("Xfer:features:read:target.xml:") {
+            let Some((off, len)) = range.split_once(',') else {
+                return "E01".to_string();
+            };
+            let (Ok(off), Ok(len)) = (
+                usize::from_str_radix(off, 16),
+                usize::from_str_radix(len, 16),
+            ) else {
+                return "E01".to_string();
+            };
+            let xml = TARGET_XML.as_bytes();
+            let start = off.min(xml.len());
+            let end = start.saturating_add(len).min(xml.len());
+            let prefix = if end == xml.len() { 'l' } else { 'm' };
+            format!("{prefix}{}", String::from_utf8_lossy(&xml[start..end]))
+        } else if args == "Attached" {
+            "1".to_string()
+        } else if args == "C" {
+            "QC1".to_string()
+        } else if args == "fThreadInfo" {
+            "m1".to_string()
+        } else if args == "sThreadInfo" {
+            "l".to_string()
+        } else {
+            String::new()
+        }
+    }
+
+    /// Read register `n` alone, with the one getter it needs.
+    async fn read_register(&self, n: usize) -> String {
+        let emu = self.emu;
+        let result = match n {
+            0..=15 => emu
+                .getVRegister(U256::from(n))
+                .call()
+                .await
+                .map(|v| vec![v._0]),
+            REG_I => emu
+                .getIRegister()
+                .call()
+                .await
+                .map(|i| i._0.to_le_bytes().to_vec()),
+            REG_PC => emu
+                .getPC()
+                .call()
+                .await
+                .map(|pc| pc._0.to_le_bytes().to_vec()),
+            REG_SP => emu
+                .getSP()
+                .call()
+                .await
+                .map(|sp| sp._0.to_le_bytes().to_vec()),
+            REG_DT => emu.getDelayTimer().call().await.map(|dt| vec![dt._0]),
+            REG_ST => emu.getSoundTimer().call().await.map(|st| vec![st._0]),
+            _ => return "E01".to_string(),
+        };
+        match result {
+            Ok(bytes) => encode_hex(&bytes),
+            Err(e) => error(e),
+        }
+    }
+
+    /// Write the registers of a `G` packet, sending transactions only for those that change.
+    async fn write_registers(&self, args: &str) -> String {
+        let bytes = decode_hex(args);
+        let current = match Chip8State::fetch(self.emu).await {
+            Ok(state) => state,
+            Err(e) => return error(e),
+        };
+        let widths = (0..=REG_ST).map(register_width);
+        let mut offset = 0;
+        for (n, width) in widths.enumerate() {
+            let Some(value) = bytes.get(offset..offset + width) else {
+                return "E01".to_string();
+            };
+            offset += width;
+            // PC has no setter; accept it unchanged so GDB can write back what it read
+            if n == REG_PC || encode_hex(value) == register(&current, n) {
+                continue;
+            }
+            let reply = self.write_register(n, value).await;
+            if reply != "OK" {
+                return reply;
+            }
+        }
+        "OK".to_string()
+    }
+
+    async fn write_register(&self, n: usize, value: &[u8]) -> String {
+        if n > REG_ST || value.len() != register_width(n) {
+            return "E01".to_string();
+        }
+        let byte = value[0];
+        let word = u16::from_le_bytes([value[0], *value.get(1).unwrap_or(&0)]);
+        let result = match n {
+            0..=15 => transact(self.emu.setVRegister(n as u8, byte)).await,
+            REG_I => transact(self.emu.setIRegister(word)).await,
+            REG_SP => transact(self.emu.setSP(word)).await,
+            REG_DT => transact(self.emu.setDelayTimer(byte)).await,
+            REG_ST => transact(self.emu.setSoundTimer(byte)).await,
+            // The contract has no way to set PC
+            _ => return "E01".to_string(),
+        };
+        match result {
+            Ok(_) => "OK".to_string(),
+            Err(e) => error(e),
+        }
+    }
+
+    async fn read_memory(&self, args: &str) -> String {
+        let Some((addr, len)) = parse_range(args) else {
+            return "E01".to_string();
+        };
+        if addr >= RAM_SIZE {
+            return "E01".to_string();
+        }
+        match read_ram(self.emu, addr, len.min(RAM_SIZE - addr)).await {
+            Ok(bytes) => encode_hex(&bytes),
+            Err(e) => error(e),
+        }
+    }
+
+    async fn write_memory(&self, args: &str) -> String {
+        let Some((range, data)) = args.split_once(':') else {
+            return "E01".to_string();
+        };
+        let Some((addr, len)) = parse_range(range) else {
+            return "E01".to_string();
+        };
+        let bytes = decode_hex(data);
+        if bytes.len() != len || addr.checked_add(len).is_none_or(|end| end > RAM_SIZE) {
+            return "E01".to_string();
+        }
+        for (i, byte) in bytes.into_iter().enumerate() {
+            let builder = self.emu.setRAMValueAt(U256::from(addr + i), byte);
+            if let Err(e) = transact(builder).await {
+                return error(e);
+            }
+        }
+        "OK".to_string()
+    }
+
+    fn breakpoint(&mut self, insert: bool, args: &str) -> String {
+        let mut fields = args.split(',');
+        // Only software and hardware execution breakpoints map to PC checks
+        if !matches!(fields.next(), Some("0" | "1")) {
+            return String::new();
+        }
+        let Some(Ok(addr)) = fields.next().map(|a| u16::from_str_radix(a, 16)) else {
+            return "E01".to_string();
+        };
+        if insert {
+            self.breakpoints.insert(addr);
+        } else {
+            self.breakpoints.remove(&addr);
+        }
+        "OK".to_string()
+    }
+
+    /// Send one `tick()` and return the stop reply.
+    async fn step(&self) -> String {
+        match self.tick().await {
+            Ok(_) => SIGTRAP.to_string(),
+            Err(reply) => reply,
+        }
+    }
+
+    /// Tick until a breakpoint is hit, `tick()` reverts or the client sends an interrupt.
+    async fn resume(&mut self) -> io::Result<String> {
+        loop {
+            let pc = match self.tick().await {
+                Ok(pc) => pc,
+                Err(reply) => return Ok(reply),
+            };
+            if self.breakpoints.contains(&pc) {
+                return Ok(format!(
+                    "T05swbreak:;{:02x}:{};",
+                    REG_PC,
+                    encode_hex(&pc.to_le_bytes())
+                ));
+            }
+            if self.poll_interrupt()? {
+                return Ok(SIGINT.to_string());
+            }
+        }
+    }
+
+    /// Send one `tick()`, returning the new PC or the stop reply for a revert.
+    async fn tick(&self) -> Result<u16, String> {
+        if let Err(e) = transact(self.emu.tick()).await {
+            println!("tick() reverted: {e}");
+            return Err(SIGILL.to_string());
+        }
+        match self.emu.getPC().call().await {
+            Ok(pc) => Ok(pc._0),
+            Err(e) => Err(error(e)),
+        }
+    }
+
+    /// Check for a `^C` from the client without blocking.
+    fn poll_interrupt(&mut self) -> io::Result<bool> {
+        let mut chunk = [0u8; 256];
+        loop {
+            match self.stream.try_read(&mut chunk) {
+                Ok(0) => return Err(io::ErrorKind::UnexpectedEof.into()),
+                Ok(n) => self.buf.extend_from_slice(&chunk[..n]),
+                Err(e) if e.kind() == io::ErrorKind::WouldBlock => break,
+                Err(e) => return Err(e),
+            }
+        }
+        match self.buf.iter().position(|b| *b == 0x03) {
+            Some(pos) => {
+                self.buf.remove(pos);
+                Ok(true)
+            }
+            None => Ok(false),
+        }
+    }
+
+    /// Read the next packet or interrupt, acknowledging packets unless in no-ack mode.
+    async fn read_packet(&mut self) -> io::Result<Option<Packet>> {
+        loop {
+            match next_frame(&mut self.buf) {
+                Some(Frame::Interrupt) => return Ok(Some(Packet::Interrupt)),
+                Some(Frame::Packet(body, valid)) => {
+                    if !self.no_ack {
+                        let ack: &[u8] = if valid { b"+" } else { b"-" };
+                        self.stream.write_all(ack).await?;
+                    }
+                    if self.no_ack || valid {
+                        return Ok(Some(Packet::Data(String::from_utf8_lossy(&body).into())));
+                    }
+                    continue;
+                }
+                None => {}
+            }
+
+            let mut chunk = [0u8; 4096];
+            let n = self.stream.read(&mut chunk).await?;
+            if n == 0 {
+                return Ok(None);
+            }
+            self.buf.extend_from_slice(&chunk[..n]);
+        }
+    }
+
+    async fn write_packet(&mut self, data: &str) -> io::Result<()> {
+        let body = escape(data.as_bytes());
+        let mut packet = Vec::with_capacity(body.len() + 4);
+        packet.push(b'$');
+        packet.extend_from_slice(&body);
+        packet.extend_from_slice(format!("#{:02x}", checksum(&body)).as_bytes());
+        self.stream.write_all(&packet).await
+    }
+}
+
+/// A whole frame taken off the front of the receive buffer.
+#[derive(Debug, PartialEq, Eq)]
+enum Frame {
+    Interrupt,
+    /// Unescaped body and whether the checksum matched.
+    Packet(Vec<u8>, bool),
+}
+
+/// Take the next frame off `buf`, or `None` until a whole one has arrived.
+fn next_frame(buf: &mut Vec<u8>) -> Option<Frame> {
+    // Acks from the client carry no information for us
+    while matches!(buf.first(), Some(b'+' | b'-')) {
+        buf.remove(0);
+    }
+    if buf.first() == Some(&0x03) {
+        buf.remove(0);
+        return Some(Frame::Interrupt);
+    }
+    let Some(start) = buf.iter().position(|b| *b == b'$') else {
+        // Garbage before a packet start
+        buf.clear();
+        return None;
+    };
+    let end = start + buf[start..].iter().position(|b| *b == b'#')?;
+    if buf.len() < end + 3 {
+        return None;
+    }
+    let raw = &buf[start + 1..end];
+    let sent = std::str::from_utf8(&buf[end + 1..end + 3])
+        .ok()
+        .and_then(|c| u8::from_str_radix(c, 16).ok());
+    let frame = Frame::Packet(unescape(raw), sent == Some(checksum(raw)));
+    buf.drain(..end + 3);
+    Some(frame)
+}
+
+fn register_width(n: usize) -> usize {
+    match n {
+        REG_I | REG_PC | REG_SP => 2,
+        _ => 1,
+    }
+}
+
+fn register(state: &Chip8State, n: usize) -> String {
+    match n {
+        0..=15 => encode_hex(&[state.v[n]]),
+        REG_I => encode_hex(&state.i.to_le_bytes()),
+        REG_PC => encode_hex(&state.pc.to_le_bytes()),
+        REG_SP => encode_hex(&state.sp.to_le_bytes()),
+        REG_DT => encode_hex(&[state.dt]),
+        _ => encode_hex(&[state.st]),
+    }
+}
+
+fn encode_registers(state: &Chip8State) -> String {
+    (0..NUM_REGS + 5).map(|n| register(state, n)).collect()
+}
+
+fn parse_range(args: &str) -> Option<(usize, usize)> {
+    let (addr, len) = args.split_once(',')?;
+    Some((
+        usize::from_str_radix(addr, 16).ok()?,
+        usize::from_str_radix(len, 16).ok()?,
+    ))
+}
+
+fn encode_hex(bytes: &[u8]) -> String {
+    alloy::hex::encode(bytes)
+}
+
+fn decode_hex(s: &str) -> Vec<u8> {
+    alloy::hex::decode(s).unwrap_or_default()
+}
+
+fn error(e: impl std::fmt::Display) -> String {
+    println!("GDB request failed: {e}");
+    "E01".to_string()
+}
+
+fn checksum(body: &[u8]) -> u8 {
+    body.iter().fold(0u8, |sum, b| sum.wrapping_add(*b))
+}
+
+/// Escape `$`, `#`, `}` and `*` as `}` followed by the byte XOR 0x20.
+fn escape(data: &[u8]) -> Vec<u8> {
+    let mut out = Vec::with_capacity(data.len());
+    for b in data {
+        if matches!(b, b'$' | b'#' | b'}' | b'*') {
+            out.extend_from_slice(&[b'}', b ^ 0x20]);
+        } else {
+            out.push(*b);
+        }
+    }
+    out
+}
+
+fn unescape(data: &[u8]) -> Vec<u8> {
+    let mut out = Vec::with_capacity(data.len());
+    let mut bytes = data.iter();
+    while let Some(b) = bytes.next() {
+        match b {
+            b'}' => out.extend(bytes.next().map(|b| b ^ 0x20)),
+            _ => out.push(*b),
+        }
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use alloy::primitives::Address;
+    use alloy::providers::ProviderBuilder;
+
+    use super::*;
+    use crate::Solchip8;
+    use desktop::client::RPC_URL;
+
+    /// `body` framed as the client sends it.
+    fn packet(body: &str) -> Vec<u8> {
+        let body = escape(body.as_bytes());
+        let mut out = vec![b'$'];
+        out.extend_from_slice(&body);
+        out.extend_from_slice(format!("#{:02x}", checksum(&body)).as_bytes());
+        out
+    }
+
+    #[test]
+    fn checksums() {
+        assert_eq!(checksum(b""), 0);
+        assert_eq!(checksum(b"OK"), 0x9a);
+        assert_eq!(checksum(b"qSupported"), 0x37);
+        assert_eq!(checksum(&[0xff, 0x02]), 0x01);
+    }
+
+    #[test]
+    fn escaping() {
+        assert_eq!(escape(b"a$b#c}d*e"), b"a}\x04b}\x03c}]d}\x0ae".to_vec());
+        assert_eq!(unescape(&escape(b"$#}*plain")), b"$#}*plain".to_vec());
+        // A trailing escape with nothing after it is dropped
+        assert_eq!(unescape(b"ab}"), b"ab".to_vec());
+    }
+
+    #[test]
+    fn framing() {
+        let mut buf = b"+-+".to_vec();
+        buf.extend(packet("g"));
+        buf.extend(packet("M200,1:24"));
+        buf.push(0x03);
+        assert_eq!(
+            next_frame(&mut buf),
+            Some(Frame::Packet(b"g".to_vec(), true))
+        );
+        assert_eq!(
+            next_frame(&mut buf),
+            Some(Frame::Packet(b"M200,1:24".to_vec(), true))
+        );
+        assert_eq!(next_frame(&mut buf), Some(Frame::Interrupt));
+        assert_eq!(next_frame(&mut buf), None);
+        assert!(buf.is_empty());
+    }
+
+    #[test]
+    fn partial_and_corrupt_frames() {
+        let whole = packet("qAttached");
+        let mut buf = whole[..whole.len() - 1].to_vec();
+        assert_eq!(next_frame(&mut buf), None);
+        buf.push(*whole.last().unwrap());
+        assert_eq!(
+            next_frame(&mut buf),
+            Some(Frame::Packet(b"qAttached".to_vec(), true))
+        );
+
+        let mut buf = b"$g#00".to_vec();
+        assert_eq!(
+            next_frame(&mut buf),
+            Some(Frame::Packet(b"g".to_vec(), false))
+        );
+        let mut buf = b"$g#zz".to_vec();
+        assert_eq!(
+            next_frame(&mut buf),
+            Some(Frame::Packet(b"g".to_vec(), false))
+        );
+
+        let mut buf = b"noise".to_vec();
+        assert_eq!(next_frame(&mut buf), None);
+        assert!(buf.is_empty());
+    }
+
+    #[test]
+    fn ranges_and_hex() {
+        assert_eq!(parse_range("200,10"), Some((0x200, 0x10)));
+        assert_eq!(parse_range("200"), None);
+        assert_eq!(parse_range("20g,1"), None);
+        assert_eq!(parse_range("200,"), None);
+        assert_eq!(decode_hex("00ff1a"), vec![0x00, 0xff, 0x1a]);
+        assert_eq!(decode_hex("zz"), Vec::<u8>::new());
+        assert_eq!(encode_hex(&[0x12, 0xab]), "12ab");
+    }
+
+    /// Replies to `packets` from a session whose contract is never reached.
+    async fn replies(packets: &[&str]) -> Vec<String> {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let _client = TcpStream::connect(listener.local_addr().unwrap())
+            .await
+            .unwrap();
+        let (stream, _) = listener.accept().await.unwrap();
+        let provider = ProviderBuilder::new().on_http(RPC_URL.parse().unwrap());
+        let emu = Solchip8::new(Address::ZERO, provider);
+        let mut session = Session {
+            emu: &emu,
+            stream,
+            buf: Vec::new(),
+            breakpoints: BTreeSet::new(),
+            no_ack: false,
+        };
+        let mut out = Vec::new();
+        for packet in packets {
+            out.push(session.handle(packet).await.unwrap());
+        }
+        out
+    }
+
+    #[tokio::test]
+    async fn handle_without_node() {
+        let out = replies(&[
+            "?",
+            "qAttached",
+            "qXfer:features:read:target.xml:0,ffffffffffffffff",
+            "QStartNoAckMode",
+            "Z0,23c,2",
+            "z0,23c,2",
+            "Z2,300,1",
+            "Mffffffffffffffff,1:00",
+            "M1000,1:00",
+            "Mfff,2:0000",
+            "m1000,1",
+            "\u{fffd}junk",
+            "é",
+        ])
+        .await;
+        assert_eq!(out[0], SIGTRAP);
+        assert_eq!(out[1], "1");
+        assert_eq!(out[2], format!("l{TARGET_XML}"));
+        assert_eq!(out[3..6], ["OK", "OK", "OK"]);
+        // Watchpoints are not supported
+        assert_eq!(out[6], "");
+        assert_eq!(out[7..11], ["E01", "E01", "E01", "E01"]);
+        assert_eq!(out[11..], ["", ""]);
+    }
+}
@@ -0,0 +1,187 @@
+//! Headless runner: ticks a ROM without SDL and writes the outcome to a directory.
+//!
+//! The run stops after `ticks` ticks, once the `until` condition holds, or when a `tick()`
+//! would revert. The output directory then holds:
+//!
+//! ```text
+//! state.txt          register panel as printed by the debugger
+//! state.json         the same registers plus the stack, tick count and why the run stopped
+//! screen.pbm         final screen
+//! <screenshot>       final screen again, as PNG or PBM by extension, if set
+//! screen-<tick>.pbm  every `screenshot_every` ticks, if set
+//! gas.json           total, mean and p99 gas per tick, and the gas of every tick
+//! ```
+//!
+//! A recording, if given, gets the screen before every tick, timed as if ticks ran at the
+//! 60Hz timer rate so the same run always gives the same file.
+
+use std::fs;
+use std::io;
+use std::path::PathBuf;
+use std::time::Duration;
+
+use alloy::network::{Network, ReceiptResponse};
+use alloy::providers::Provider;
+use alloy::transports::Transport;
+use desktop::client;
+use desktop::input::Script;
+use serde_json::json;
+
+use crate::bench::GasStats;
+use crate::expr::Expr;
+use crate::record::Recorder;
+use crate::replay::{self, Recording};
+use crate::screenshot::{self, Style};
+use crate::state::Chip8State;
+use crate::Solchip8::Solchip8Instance;
+
+pub struct Options {
+    pub ticks: u64,
+    pub until: Option<Expr>,
+    pub input: Script,
+    pub out: PathBuf,
+    pub screenshot_every: Option<u64>,
+    /// Extra file to save the final screen to.
+    pub screenshot: Option<PathBuf>,
+    pub style: Style,
+    /// Send each tick's key changes as one canonical batch, as a replay does, so a pinned
+    /// run builds the same blocks as its replay.
+    pub deterministic: bool,
+}
+
+/// Why the run ended.
+enum Stop {
+    Ticks,
+    Condition,
+    Revert(String),
+}
+
+/// Ticks per second of recording time.
+const TICK_RATE: u32 = 60;
+
+/// Run the program loaded in `emu`. Returns false if a tick reverted, or if `until` was given
+/// and never held.
+pub async fn run<T, P, N>(
+    emu: &Solchip8Instance<T, P, N>,
+    opts: &Options,
+    mut recorder: Option<Recorder>,
+    mut recording: Option<Recording>,
+) -> io::Result<bool>
+where
+    T: Transport + Clone,
+    P: Provider<T, N>,
+    N: Network,
+{
+    fs::create_dir_all(&opts.out)?;
+    let mut gas = Vec::new();
+    let mut stop = Stop::Ticks;
+    let mut keys = 0u16;
+    for tick in 0..opts.ticks {
+        if let Some(until) = &opts.until {
+            let state = Chip8State::fetch(emu).await.map_err(io::Error::other)?;
+            if until.holds(&state, emu).await.map_err(until_failed)? {
+                stop = Stop::Condition;
+                break;
+            }
+        }
+        if opts
+            .screenshot_every
+            .is_some_and(|every| tick.is_multiple_of(every))
+        {
+            let path = opts.out.join(format!("screen-{tick:06}.pbm"));
+            screenshot::capture(emu, &path, &opts.style).await?;
+        }
+        if let Some(recorder) = recorder.as_mut() {
+            recorder.capture(emu, tick_time(tick)).await?;
+        }
+        if opts.deterministic {
+            let held = opts.input.held(tick, keys);
+            replay::send_keys(emu, keys, held)
+                .await
+                .map_err(io::Error::other)?;
+            keys = held;
+        } else {
+            opts.input
+                .apply(tick, emu)
+                .await
+                .map_err(io::Error::other)?;
+        }
+        match client::transact(emu.tick()).await {
+            Ok(receipt) => gas.push(receipt.gas_used() as u64),
+            Err(e) => {
+                stop = Stop::Revert(client::revert_reason(&e));
+                break;
+            }
+        }
+        if let Some(recording) = recording.as_mut() {
+            recording.tick(emu).await?;
+        }
+    }
+
+    let state = Chip8State::fetch(emu).await.map_err(io::Error::other)?;
+    // The loop only checks before a tick, so the last tick's result hasn't been checked yet
+    if let (Stop::Ticks, Some(until)) = (&stop, &opts.until) {
+        if until.holds(&state, emu).await.map_err(until_failed)? {
+            stop = Stop::Condition;
+        }
+    }
+    let (reason, ok) = match &stop {
+        Stop::Ticks => (format!("ran {} ticks", gas.len()), opts.until.is_none()),
+        Stop::Condition => (format!("condition met after {} ticks", gas.len()), true),
+        Stop::Revert(e) => (format!("tick {} reverted: {e}", gas.len()), false),
+    };
+    println!("{reason}");
+    println!("{state}");
+
+    fs::write(opts.out.join("state.txt"), format!("{state}\n"))?;
+    let state_json = json!({
+        "ticks": gas.len(),
+        "stopped": reason,
+        "pc": state.pc,
+        "opcode": state.opcode,
+        "v": state.v,
+        "i": state.i,
+        "sp": state.sp,
+        "stack": state.stack,
+        "dt": state.dt,
+        "st": state.st,
+        "keys": state.keys,
+    });
+    fs::write(opts.out.join("state.json"), format!("{state_json:#}\n"))?;
+
+    screenshot::capture(emu, &opts.out.join("screen.pbm"), &opts.style).await?;
+    if let Some(mut recorder) = recorder {
+        let ticks = gas.len() as u64;
+        recorder.capture(emu, tick_time(ticks)).await?;
+        let frames = recorder.finish(tick_time(ticks + 1))?;
+        println!("recorded {frames} frames");
+    }
+    if let Some(recording) = recording {
+        let ticks = recording.finish(emu).await?;
+        println!("saved replay of {ticks} ticks");
+    }
+    if let Some(path) = &opts.screenshot {
+        screenshot::capture(emu, path, &opts.style).await?;
+    }
+
+    let stats = GasStats::new(&gas);
+    println!("gas ({stats})");
+    let gas_json = json!({
+        "ticks": stats.ticks,
+        "total": stats.total,
+        "mean": stats.mean,
+        "p99": stats.p99,
+        "per_tick": gas,
+    });
+    fs::write(opts.out.join("gas.json"), format!("{gas_json:#}\n"))?;
+    println!("wrote {}", opts.out.display());
+    Ok(ok)
+}
+
+fn tick_time(tick: u64) -> Duration {
+    Duration::from_secs(tick) / TICK_RATE
+}
+
+fn until_failed(e: alloy::contract::Error) -> io::Error {
+    io::Error::other(format!("evaluating --until failed: {e}"))
+}
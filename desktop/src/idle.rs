@@ -0,0 +1,101 @@
+//! Detecting programs that cannot make progress, so the frontends stop sending `tick()`
+//! transactions that change nothing.
+//!
+//! With both timers at zero, a `tick()` on a `1NNN` jumping to itself only lands on the same
+//! jump again, and one on `FX0A` with no key held moves PC back to the `FX0A`. Either way
+//! only a key press or a write to the state can change what the next `tick()` does.
+
+use std::fmt;
+
+use alloy::network::Network;
+use alloy::providers::Provider;
+use alloy::transports::Transport;
+
+use crate::state::read_opcode;
+use crate::Solchip8::Solchip8Instance;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Idle {
+    /// `JP` to itself at `pc`, as games do when they end.
+    Halted { pc: u16 },
+    /// `FX0A` at `pc` with no key held.
+    WaitingForKey { pc: u16 },
+}
+
+impl fmt::Display for Idle {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Idle::Halted { pc } => write!(f, "halted at {pc:#05x}"),
+            Idle::WaitingForKey { pc } => write!(f, "waiting for key at {pc:#05x}"),
+        }
+    }
+}
+
+/// Whether the instruction `opcode` at `pc` leaves the program idle with `keys` held and the
+/// given timers.
+pub fn detect(pc: u16, opcode: u16, keys: u16, dt: u8, st: u8) -> Option<Idle> {
+    if dt != 0 || st != 0 {
+        return None;
+    }
+    if opcode == 0x1000 | pc {
+        Some(Idle::Halted { pc })
+    } else if opcode & 0xF0FF == 0xF00A && keys == 0 {
+        Some(Idle::WaitingForKey { pc })
+    } else {
+        None
+    }
+}
+
+/// Read PC, the opcode there, the keys and the timers from the contract and `detect`.
+pub async fn check<T, P, N>(
+    emu: &Solchip8Instance<T, P, N>,
+) -> Result<Option<Idle>, alloy::contract::Error>
+where
+    T: Transport + Clone,
+    P: Provider<T, N>,
+    N: Network,
+{
+    let pc = emu.getPC().call().await?._0;
+    let opcode = read_opcode(emu, pc).await?;
+    let keys = emu.getKeys().call().await?._0;
+    let dt = emu.getDelayTimer().call().await?._0;
+    let st = emu.getSoundTimer().call().await?._0;
+    Ok(detect(pc, opcode, keys, dt, st))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn jump_to_itself_halts() {
+        assert_eq!(
+            detect(0x228, 0x1228, 0, 0, 0),
+            Some(Idle::Halted { pc: 0x228 })
+        );
+        // Held keys don't matter, nothing reads them
+        assert_eq!(
+            detect(0x228, 0x1228, 0x0010, 0, 0),
+            Some(Idle::Halted { pc: 0x228 })
+        );
+        assert_eq!(detect(0x228, 0x1200, 0, 0, 0), None);
+        assert_eq!(detect(0x228, 0x2228, 0, 0, 0), None);
+    }
+
+    #[test]
+    fn key_wait_without_keys_idles() {
+        assert_eq!(
+            detect(0x300, 0xF30A, 0, 0, 0),
+            Some(Idle::WaitingForKey { pc: 0x300 })
+        );
+        assert_eq!(detect(0x300, 0xF30A, 0x8000, 0, 0), None);
+        assert_eq!(detect(0x300, 0xF307, 0, 0, 0), None);
+    }
+
+    #[test]
+    fn running_timers_are_progress() {
+        assert_eq!(detect(0x228, 0x1228, 0, 1, 0), None);
+        assert_eq!(detect(0x228, 0x1228, 0, 0, 1), None);
+        assert_eq!(detect(0x300, 0xF30A, 0, 0, 3), None);
+    }
+}
@@ -0,0 +1,112 @@
+//! Scripted key input for runs without a keyboard.
+//!
+//! A script has one event per line, `<tick> <key> down|up`, with the key as a hex digit 0-F.
+//! The event is sent with `keypress()` before that tick's `tick()`; `#` starts a comment.
+//!
+//! ```text
+//! # start Space Invaders, then fire for a while
+//! 10  5 down
+//! 12  5 up
+//! 40  5 down
+//! 300 5 up
+//! ```
+
+use std::collections::BTreeMap;
+use std::fs;
+
+use alloy::network::Network;
+use alloy::primitives::U256;
+use alloy::providers::Provider;
+use alloy::transports::Transport;
+
+use crate::client;
+use crate::Solchip8::Solchip8Instance;
+
+#[derive(Debug, Clone, Default)]
+pub struct Script {
+    /// Key changes by tick, as (key, pressed).
+    events: BTreeMap<u64, Vec<(u8, bool)>>,
+}
+
+impl Script {
+    pub fn load(path: &str) -> Result<Self, String> {
+        let source = fs::read_to_string(path).map_err(|e| format!("{path}: {e}"))?;
+        Script::parse(&source)
+    }
+
+    pub fn parse(source: &str) -> Result<Self, String> {
+        let mut script = Script::default();
+        for (n, line) in source.lines().enumerate() {
+            let line = line.split('#').next().unwrap_or_default();
+            let fields: Vec<_> = line.split_whitespace().collect();
+            let event = match fields[..] {
+                [] => continue,
+                [tick, key, action] => {
+                    let tick = tick.parse().map_err(|_| format!("invalid tick `{tick}`"));
+                    let key = u8::from_str_radix(key, 16)
+                        .ok()
+                        .filter(|key| *key < 16)
+                        .ok_or(format!("invalid key `{key}`, expected 0-F"));
+                    let pressed = match action {
+                        "down" => Ok(true),
+                        "up" => Ok(false),
+                        _ => Err(format!("invalid action `{action}`, expected down or up")),
+                    };
+                    tick.and_then(|tick| Ok((tick, key?, pressed?)))
+                }
+                _ => Err("expected `<tick> <key> down|up`".to_string()),
+            };
+            let (tick, key, pressed) = event.map_err(|e| format!("line {}: {e}", n + 1))?;
+            script.press(tick, key, pressed);
+        }
+        Ok(script)
+    }
+
+    /// Tap every key in turn, 0 to F, one every 25 ticks and held for 5, over `ticks` ticks.
+    ///
+    /// Enough to get past "press any key" screens and move most games around.
+    pub fn cycle_keys(ticks: u64) -> Self {
+        let mut script = Script::default();
+        for (n, tick) in (0..ticks).step_by(25).enumerate() {
+            let key = (n % 16) as u8;
+            script.press(tick, key, true);
+            script.press(tick + 5, key, false);
+        }
+        script
+    }
+
+    pub fn press(&mut self, tick: u64, key: u8, pressed: bool) {
+        self.events.entry(tick).or_default().push((key, pressed));
+    }
+
+    /// Key changes scheduled for `tick`, as (key, pressed).
+    pub fn at(&self, tick: u64) -> impl Iterator<Item = (u8, bool)> + '_ {
+        self.events.get(&tick).into_iter().flatten().copied()
+    }
+
+    /// Keys held once the changes scheduled for `tick` are applied to `keys`.
+    pub fn held(&self, tick: u64, keys: u16) -> u16 {
+        self.at(tick)
+            .fold(keys, |keys, (key, pressed)| match pressed {
+                true => keys | 1 << key,
+                false => keys & !(1 << key),
+            })
+    }
+
+    /// Send the key changes scheduled for `tick`.
+    pub async fn apply<T, P, N>(
+        &self,
+        tick: u64,
+        emu: &Solchip8Instance<T, P, N>,
+    ) -> Result<(), alloy::contract::Error>
+    where
+        T: Transport + Clone,
+        P: Provider<T, N>,
+        N: Network,
+    {
+        for (key, pressed) in self.at(tick) {
+            client::transact(emu.keypress(U256::from(key), pressed)).await?;
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,217 @@
+//! Transport latency benchmark: the same requests against one node over each URL, so HTTP,
+//! WebSocket and IPC can be compared before picking one with `--rpc-url`.
+//!
+//! Every URL gets its own contract running a `JP` to itself, so every tick costs the same.
+//! `tick` is a whole `client::transact`, simulated, sent and waited for, as the frontends do
+//! it. `newHeads` runs from sending a tick until the subscription announces its block, on
+//! transports that have subscriptions. With anvil mining on every transaction these are round
+//! trips; with interval mining they include the wait for the next block.
+
+use std::fmt::{self, Write};
+use std::future::Future;
+use std::io;
+use std::time::{Duration, Instant};
+
+use alloy::providers::Provider;
+use alloy::rpc::types::Header;
+use desktop::client;
+
+use crate::rom::Rom;
+use crate::Solchip8;
+
+/// `JP 0x200`, so every tick lands on the same instruction.
+const PROGRAM: [u8; 2] = [0x12, 0x00];
+
+/// Median and 99th percentile of one kind of request.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Latency {
+    pub p50: Duration,
+    pub p99: Duration,
+}
+
+impl Latency {
+    pub fn new(samples: &[Duration]) -> Self {
+        let mut sorted = samples.to_vec();
+        sorted.sort_unstable();
+        let at = |percent: usize| match sorted.len() {
+            0 => Duration::ZERO,
+            len => sorted[(len * percent).div_ceil(100) - 1],
+        };
+        Latency {
+            p50: at(50),
+            p99: at(99),
+        }
+    }
+}
+
+impl fmt::Display for Latency {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let ms = |d: Duration| d.as_secs_f64() * 1000.0;
+        write!(f, "{:.2} / {:.2}", ms(self.p50), ms(self.p99))
+    }
+}
+
+/// One row of the report.
+#[derive(Debug, Clone)]
+pub struct Report {
+    pub url: String,
+    pub transport: &'static str,
+    pub block_number: Latency,
+    pub get_display: Latency,
+    pub tick: Latency,
+    /// `None` on transports without subscriptions.
+    pub new_heads: Option<Latency>,
+}
+
+/// Time `rounds` runs of `request`.
+async fn time<F, Fut, R, E>(rounds: u32, mut request: F) -> io::Result<Latency>
+where
+    F: FnMut() -> Fut,
+    Fut: Future<Output = Result<R, E>>,
+    E: fmt::Display,
+{
+    let mut samples = Vec::with_capacity(rounds as usize);
+    for _ in 0..rounds {
+        let start = Instant::now();
+        request()
+            .await
+            .map_err(|e| io::Error::other(e.to_string()))?;
+        samples.push(start.elapsed());
+    }
+    Ok(Latency::new(&samples))
+}
+
+/// Measure every request `rounds` times over `url`.
+pub async fn measure(url: &str, rounds: u32) -> io::Result<Report> {
+    let other = |what: &str, e: &dyn fmt::Display| io::Error::other(format!("{what}: {e}"));
+    let transport = client::transport(url).map_err(|e| other("invalid URL", &e))?;
+    let provider = client::connect(url)
+        .await
+        .map_err(|e| other("connecting failed", &e))?;
+    let emu = Solchip8::deploy(provider.clone())
+        .await
+        .map_err(|e| other("deploy failed", &e))?;
+    Rom::from_bytes(PROGRAM.to_vec())
+        .map_err(|e| other("ROM", &e))?
+        .load(&emu)
+        .await
+        .map_err(|e| other("ROM load failed", &e))?;
+
+    let block_number = time(rounds, || provider.get_block_number()).await?;
+    let get_display = time(rounds, || async { emu.getDisplay().call().await }).await?;
+    let tick = time(rounds, || client::transact(emu.tick())).await?;
+    let new_heads = match provider.subscribe::<_, Header>(("newHeads",)).await {
+        Ok(mut heads) => {
+            let mut samples = Vec::with_capacity(rounds as usize);
+            for _ in 0..rounds {
+                let start = Instant::now();
+                // The head announcing the block is what is timed, not the receipt
+                let _pending = emu.tick().send().await.map_err(|e| other("tick", &e))?;
+                heads.recv().await.map_err(|e| other("newHeads", &e))?;
+                samples.push(start.elapsed());
+            }
+            Some(Latency::new(&samples))
+        }
+        Err(_) => None,
+    };
+
+    Ok(Report {
+        url: url.to_string(),
+        transport,
+        block_number,
+        get_display,
+        tick,
+        new_heads,
+    })
+}
+
+/// Measure every URL in turn.
+pub async fn run(urls: &[String], rounds: u32) -> io::Result<Vec<Report>> {
+    let mut reports = Vec::new();
+    for url in urls {
+        let report = measure(url, rounds)
+            .await
+            .map_err(|e| io::Error::other(format!("{url}: {e}")))?;
+        println!("{url}: tick {}", report.tick);
+        reports.push(report);
+    }
+    Ok(reports)
+}
+
+/// The reports as a Markdown table, p50 / p99 in milliseconds.
+pub fn render(reports: &[Report], rounds: u32) -> String {
+    let mut out = format!(
+        "p50 / p99 ms over {rounds} rounds\n\n\
+         | transport | URL | eth_blockNumber | getDisplay | tick | newHeads |\n\
+         |-----------|-----|----------------:|-----------:|-----:|---------:|\n"
+    );
+    for report in reports {
+        let new_heads = match report.new_heads {
+            Some(latency) => latency.to_string(),
+            None => "-".to_string(),
+        };
+        writeln!(
+            out,
+            "| {} | {} | {} | {} | {} | {} |",
+            report.transport,
+            report.url,
+            report.block_number,
+            report.get_display,
+            report.tick,
+            new_heads
+        )
+        .unwrap();
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn latency(p50_us: u64, p99_us: u64) -> Latency {
+        Latency {
+            p50: Duration::from_micros(p50_us),
+            p99: Duration::from_micros(p99_us),
+        }
+    }
+
+    #[test]
+    fn render_has_a_row_per_url() {
+        let reports = [
+            Report {
+                url: "http://localhost:8545".to_string(),
+                transport: "http",
+                block_number: latency(250, 1_000),
+                get_display: latency(400, 1_250),
+                tick: latency(3_000, 12_340),
+                new_heads: None,
+            },
+            Report {
+                url: "ws://localhost:8545".to_string(),
+                transport: "ws",
+                block_number: latency(120, 300),
+                get_display: latency(200, 500),
+                tick: latency(2_500, 4_000),
+                new_heads: Some(latency(2_600, 4_100)),
+            },
+        ];
+        let table = render(&reports, 20);
+        let lines: Vec<_> = table.lines().collect();
+        assert_eq!(lines.len(), 6);
+        assert_eq!(lines[0], "p50 / p99 ms over 20 rounds");
+        assert_eq!(lines[1], "");
+        assert_eq!(
+            lines[2],
+            "| transport | URL | eth_blockNumber | getDisplay | tick | newHeads |"
+        );
+        assert_eq!(
+            lines[4],
+            "| http | http://localhost:8545 | 0.25 / 1.00 | 0.40 / 1.25 | 3.00 / 12.34 | - |"
+        );
+        assert_eq!(
+            lines[5],
+            "| ws | ws://localhost:8545 | 0.12 / 0.30 | 0.20 / 0.50 | 2.50 / 4.00 | 2.60 / 4.10 |"
+        );
+    }
+}
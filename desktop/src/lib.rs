@@ -0,0 +1,13 @@
+//! The contract bindings and the parts of the client that integration tests share with the
+//! binary.
+
+pub mod client;
+pub mod contract;
+pub mod display;
+pub mod input;
+pub mod random;
+
+pub use contract::Solchip8;
+
+pub const SCREEN_WIDTH: usize = 64;
+pub const SCREEN_HEIGHT: usize = 32;
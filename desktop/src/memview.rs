@@ -0,0 +1,379 @@
+//! Hex view of the contract's RAM in a second SDL window, editable through `setRAMValueAt`.
+//!
+//! Digits are drawn with the CHIP-8 font itself, so no font library is needed. The font area
+//! (0x000-0x04F) and the loaded program (from 0x200) are shaded, the two bytes at PC are
+//! outlined in yellow and the byte at I in magenta.
+//!
+//! Click a cell or move with the arrow keys, then type two hex digits to write the byte.
+//! PageUp/PageDown and the mouse wheel scroll, Home jumps to PC and End to I.
+
+use alloy::network::Network;
+use alloy::primitives::U256;
+use alloy::providers::Provider;
+use alloy::transports::{Transport, TransportResult};
+use desktop::client;
+use sdl2::event::{Event, WindowEvent};
+use sdl2::keyboard::Keycode;
+use sdl2::mouse::MouseButton;
+use sdl2::pixels::Color;
+use sdl2::rect::Rect;
+use sdl2::render::Canvas;
+use sdl2::video::Window;
+use sdl2::VideoSubsystem;
+
+use crate::rom::{FONTSET_SIZE, RAM_SIZE, START_ADDR};
+use crate::storage;
+use crate::Solchip8::Solchip8Instance;
+
+/// The contract's `FONTSET`: 4x5 sprites for the hex digits 0-F, five bytes each.
+const FONTSET: [u8; FONTSET_SIZE] = [
+    0xF0, 0x90, 0x90, 0x90, 0xF0, // 0
+    0x20, 0x60, 0x20, 0x20, 0x70, // 1
+    0xF0, 0x10, 0xF0, 0x80, 0xF0, // 2
+    0xF0, 0x10, 0xF0, 0x10, 0xF0, // 3
+    0x90, 0x90, 0xF0, 0x10, 0x10, // 4
+    0xF0, 0x80, 0xF0, 0x10, 0xF0, // 5
+    0xF0, 0x80, 0xF0, 0x90, 0xF0, // 6
+    0xF0, 0x10, 0x20, 0x40, 0x40, // 7
+    0xF0, 0x90, 0xF0, 0x90, 0xF0, // 8
+    0xF0, 0x90, 0xF0, 0x10, 0xF0, // 9
+    0xF0, 0x90, 0xF0, 0x90, 0x90, // A
+    0xE0, 0x90, 0xE0, 0x90, 0xE0, // B
+    0xF0, 0x80, 0x80, 0x80, 0xF0, // C
+    0xE0, 0x90, 0x90, 0x90, 0xE0, // D
+    0xF0, 0x80, 0xF0, 0x80, 0xF0, // E
+    0xF0, 0x80, 0xF0, 0x80, 0x80, // F
+];
+
+/// Storage slot of `emu.program_size`, which has no getter, see `storage`.
+const PROGRAM_SIZE_SLOT: usize = 144;
+
+/// Bytes per row.
+const COLS: usize = 16;
+/// Rows visible at once.
+const ROWS: usize = 32;
+const TOTAL_ROWS: usize = RAM_SIZE / COLS;
+
+/// Size of one font pixel.
+const DOT: u32 = 2;
+/// A 4x5 glyph plus one column of spacing.
+pub const DIGIT_WIDTH: u32 = 5 * DOT;
+const CELL_WIDTH: u32 = 2 * DIGIT_WIDTH + 6;
+const ROW_HEIGHT: u32 = 5 * DOT + 6;
+const ADDR_WIDTH: u32 = 3 * DIGIT_WIDTH + 12;
+const MARGIN: u32 = 6;
+const WINDOW_WIDTH: u32 = 2 * MARGIN + ADDR_WIDTH + COLS as u32 * CELL_WIDTH;
+const WINDOW_HEIGHT: u32 = 2 * MARGIN + ROWS as u32 * ROW_HEIGHT;
+
+const BACKGROUND: Color = Color::RGB(0, 0, 0);
+const TEXT: Color = Color::RGB(200, 200, 200);
+const ADDR_TEXT: Color = Color::RGB(110, 110, 110);
+const PENDING_TEXT: Color = Color::RGB(255, 160, 0);
+const FONT_AREA: Color = Color::RGB(30, 40, 90);
+const PROGRAM_AREA: Color = Color::RGB(20, 70, 30);
+const PC_MARK: Color = Color::RGB(255, 220, 0);
+const I_MARK: Color = Color::RGB(230, 0, 230);
+const SELECTED: Color = Color::RGB(255, 255, 255);
+
+pub struct MemoryView {
+    canvas: Canvas<Window>,
+    /// First visible row.
+    top: usize,
+    selected: usize,
+    /// High nibble typed for the selected byte, waiting for the low one.
+    pending: Option<u8>,
+}
+
+impl MemoryView {
+    pub fn new(video: &VideoSubsystem) -> Self {
+        let window = video
+            .window("Chip-8 RAM", WINDOW_WIDTH, WINDOW_HEIGHT)
+            .opengl()
+            .build()
+            .unwrap();
+        let canvas = window.into_canvas().build().unwrap();
+        MemoryView {
+            canvas,
+            top: START_ADDR / COLS,
+            selected: START_ADDR,
+            pending: None,
+        }
+    }
+
+    /// Whether `event` was sent to this window.
+    pub fn owns(&self, event: &Event) -> bool {
+        let id = self.canvas.window().id();
+        match *event {
+            Event::Window { window_id, .. }
+            | Event::KeyDown { window_id, .. }
+            | Event::KeyUp { window_id, .. }
+            | Event::MouseButtonDown { window_id, .. }
+            | Event::MouseWheel { window_id, .. } => window_id == id,
+            _ => false,
+        }
+    }
+
+    /// Handle an event for this window. Returns false once the window is closed.
+    pub async fn handle<T, P, N>(
+        &mut self,
+        event: &Event,
+        emu: &Solchip8Instance<T, P, N>,
+    ) -> Result<bool, alloy::contract::Error>
+    where
+        T: Transport + Clone,
+        P: Provider<T, N>,
+        N: Network,
+    {
+        match *event {
+            Event::Window {
+                win_event: WindowEvent::Close,
+                ..
+            }
+            | Event::KeyDown {
+                keycode: Some(Keycode::Escape),
+                ..
+            } => return Ok(false),
+            Event::KeyDown {
+                keycode: Some(key), ..
+            } => self.key(key, emu).await?,
+            Event::MouseButtonDown {
+                mouse_btn: MouseButton::Left,
+                x,
+                y,
+                ..
+            } => {
+                if let Some(addr) = self.cell_at(x, y) {
+                    self.select(addr);
+                }
+            }
+            Event::MouseWheel { y, .. } => self.scroll(-y as isize * 4),
+            _ => (),
+        }
+        Ok(true)
+    }
+
+    async fn key<T, P, N>(
+        &mut self,
+        key: Keycode,
+        emu: &Solchip8Instance<T, P, N>,
+    ) -> Result<(), alloy::contract::Error>
+    where
+        T: Transport + Clone,
+        P: Provider<T, N>,
+        N: Network,
+    {
+        let step = match key {
+            Keycode::Left => -1,
+            Keycode::Right => 1,
+            Keycode::Up => -(COLS as isize),
+            Keycode::Down => COLS as isize,
+            Keycode::PageUp => -((ROWS * COLS) as isize),
+            Keycode::PageDown => (ROWS * COLS) as isize,
+            Keycode::Home => {
+                let pc = emu.getPC().call().await?._0;
+                self.select(pc as usize);
+                return Ok(());
+            }
+            Keycode::End => {
+                let i = emu.getIRegister().call().await?._0;
+                self.select(i as usize);
+                return Ok(());
+            }
+            Keycode::Backspace => {
+                self.pending = None;
+                return Ok(());
+            }
+            _ => {
+                if let Some(digit) = key2hex(key) {
+                    self.type_digit(digit, emu).await;
+                }
+                return Ok(());
+            }
+        };
+        let addr = (self.selected as isize + step).clamp(0, RAM_SIZE as isize - 1);
+        self.select(addr as usize);
+        Ok(())
+    }
+
+    async fn type_digit<T, P, N>(&mut self, digit: u8, emu: &Solchip8Instance<T, P, N>)
+    where
+        T: Transport + Clone,
+        P: Provider<T, N>,
+        N: Network,
+    {
+        let Some(high) = self.pending.take() else {
+            self.pending = Some(digit);
+            return;
+        };
+        let value = high << 4 | digit;
+        let builder = emu.setRAMValueAt(U256::from(self.selected), value);
+        match client::transact(builder).await {
+            Ok(_) => println!("RAM[{:#05x}] = {:#04x}", self.selected, value),
+            Err(e) => println!("RAM[{:#05x}] write failed: {}", self.selected, e),
+        }
+        self.select((self.selected + 1).min(RAM_SIZE - 1));
+    }
+
+    fn select(&mut self, addr: usize) {
+        self.selected = addr.min(RAM_SIZE - 1);
+        self.pending = None;
+        let row = self.selected / COLS;
+        if row < self.top {
+            self.top = row;
+        } else if row >= self.top + ROWS {
+            self.top = row + 1 - ROWS;
+        }
+    }
+
+    fn scroll(&mut self, rows: isize) {
+        let top = (self.top as isize + rows).clamp(0, (TOTAL_ROWS - ROWS) as isize);
+        self.top = top as usize;
+    }
+
+    fn cell_at(&self, x: i32, y: i32) -> Option<usize> {
+        let x = u32::try_from(x - (MARGIN + ADDR_WIDTH) as i32).ok()?;
+        let y = u32::try_from(y - MARGIN as i32).ok()?;
+        let (col, row) = ((x / CELL_WIDTH) as usize, (y / ROW_HEIGHT) as usize);
+        (col < COLS && row < ROWS).then(|| (self.top + row) * COLS + col)
+    }
+
+    fn cell_rect(&self, addr: usize) -> Option<Rect> {
+        let row = (addr / COLS).checked_sub(self.top).filter(|&row| row < ROWS)?;
+        let x = MARGIN + ADDR_WIDTH + (addr % COLS) as u32 * CELL_WIDTH;
+        let y = MARGIN + row as u32 * ROW_HEIGHT;
+        Some(Rect::new(x as i32, y as i32, CELL_WIDTH - 2, ROW_HEIGHT - 2))
+    }
+
+    /// Redraw the visible rows from the contract's current RAM.
+    pub async fn draw<T, P, N>(
+        &mut self,
+        emu: &Solchip8Instance<T, P, N>,
+    ) -> Result<(), alloy::contract::Error>
+    where
+        T: Transport + Clone,
+        P: Provider<T, N>,
+        N: Network,
+    {
+        let start = self.top * COLS;
+        let ram = storage::read_ram(emu, start, ROWS * COLS).await?;
+        let program_size = read_program_size(emu).await?;
+        let pc = emu.getPC().call().await?._0 as usize;
+        let i = emu.getIRegister().call().await?._0 as usize;
+
+        self.canvas.set_draw_color(BACKGROUND);
+        self.canvas.clear();
+
+        let mut font_area = Vec::new();
+        let mut program_area = Vec::new();
+        let mut text = Vec::new();
+        let mut addr_text = Vec::new();
+        for (offset, &byte) in ram.iter().enumerate() {
+            let addr = start + offset;
+            let rect = self.cell_rect(addr).unwrap();
+            if addr < FONTSET_SIZE {
+                font_area.push(rect);
+            } else if (START_ADDR..START_ADDR + program_size).contains(&addr) {
+                program_area.push(rect);
+            }
+            if addr.is_multiple_of(COLS) {
+                let y = rect.y() + 3;
+                for (n, shift) in [8, 4, 0].into_iter().enumerate() {
+                    let x = (MARGIN + n as u32 * DIGIT_WIDTH) as i32;
+                    glyph(&mut addr_text, x, y, (addr >> shift) as u8 & 0xF);
+                }
+            }
+            if addr == self.selected && self.pending.is_some() {
+                continue;
+            }
+            glyph(&mut text, rect.x() + 3, rect.y() + 3, byte >> 4);
+            glyph(&mut text, rect.x() + 3 + DIGIT_WIDTH as i32, rect.y() + 3, byte & 0xF);
+        }
+
+        fill(&mut self.canvas, FONT_AREA, &font_area);
+        fill(&mut self.canvas, PROGRAM_AREA, &program_area);
+        fill(&mut self.canvas, ADDR_TEXT, &addr_text);
+        fill(&mut self.canvas, TEXT, &text);
+
+        if let (Some(high), Some(rect)) = (self.pending, self.cell_rect(self.selected)) {
+            let mut pending = Vec::new();
+            glyph(&mut pending, rect.x() + 3, rect.y() + 3, high);
+            fill(&mut self.canvas, PENDING_TEXT, &pending);
+        }
+
+        let marks = [
+            (PC_MARK, pc),
+            (PC_MARK, pc + 1),
+            (I_MARK, i),
+            (SELECTED, self.selected),
+        ];
+        for (color, addr) in marks {
+            if let Some(rect) = self.cell_rect(addr) {
+                self.canvas.set_draw_color(color);
+                self.canvas.draw_rect(rect).unwrap();
+            }
+        }
+        self.canvas.present();
+        Ok(())
+    }
+}
+
+/// Size of the program last passed to `load()`.
+async fn read_program_size<T, P, N>(emu: &Solchip8Instance<T, P, N>) -> TransportResult<usize>
+where
+    T: Transport + Clone,
+    P: Provider<T, N>,
+    N: Network,
+{
+    let size = emu
+        .provider()
+        .get_storage_at(*emu.address(), U256::from(PROGRAM_SIZE_SLOT))
+        .await?;
+    Ok(size.saturating_to())
+}
+
+/// Queue the pixels of hex digit `digit` with its top-left corner at (`x`, `y`).
+pub fn glyph(rects: &mut Vec<Rect>, x: i32, y: i32, digit: u8) {
+    let rows = &FONTSET[digit as usize * 5..][..5];
+    sprite(rects, x, y, rows, 4, DOT);
+}
+
+/// Queue the set bits of a CHIP-8 sprite, `width` bits per row read from the MSB, as
+/// `dot`-sized squares.
+pub fn sprite(rects: &mut Vec<Rect>, x: i32, y: i32, rows: &[u8], width: u32, dot: u32) {
+    for (dy, row) in rows.iter().enumerate() {
+        for dx in 0..width {
+            if row & (0x80 >> dx) != 0 {
+                let px = x + (dx * dot) as i32;
+                let py = y + (dy as u32 * dot) as i32;
+                rects.push(Rect::new(px, py, dot, dot));
+            }
+        }
+    }
+}
+
+pub fn fill(canvas: &mut Canvas<Window>, color: Color, rects: &[Rect]) {
+    canvas.set_draw_color(color);
+    canvas.fill_rects(rects).unwrap();
+}
+
+fn key2hex(key: Keycode) -> Option<u8> {
+    match key {
+        Keycode::Num0 | Keycode::Kp0 => Some(0x0),
+        Keycode::Num1 | Keycode::Kp1 => Some(0x1),
+        Keycode::Num2 | Keycode::Kp2 => Some(0x2),
+        Keycode::Num3 | Keycode::Kp3 => Some(0x3),
+        Keycode::Num4 | Keycode::Kp4 => Some(0x4),
+        Keycode::Num5 | Keycode::Kp5 => Some(0x5),
+        Keycode::Num6 | Keycode::Kp6 => Some(0x6),
+        Keycode::Num7 | Keycode::Kp7 => Some(0x7),
+        Keycode::Num8 | Keycode::Kp8 => Some(0x8),
+        Keycode::Num9 | Keycode::Kp9 => Some(0x9),
+        Keycode::A => Some(0xA),
+        Keycode::B => Some(0xB),
+        Keycode::C => Some(0xC),
+        Keycode::D => Some(0xD),
+        Keycode::E => Some(0xE),
+        Keycode::F => Some(0xF),
+        _ => None,
+    }
+}
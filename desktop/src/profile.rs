@@ -0,0 +1,231 @@
+//! Gas profiler: replays each `tick()` with `debug_traceTransaction` and attributes its cost to
+//! the CHIP-8 opcode family that tick executed.
+//!
+//! The struct log of every traced tick also gives the storage slots read and written, which are
+//! named after the layout in `storage`, and the gas spent per EVM opcode.
+
+use std::collections::BTreeMap;
+use std::fmt;
+
+use alloy::network::Network;
+use alloy::primitives::{TxHash, U256};
+use alloy::providers::ext::DebugApi;
+use alloy::providers::Provider;
+use alloy::rpc::types::trace::geth::{
+    GethDebugTracingOptions, GethDefaultTracingOptions, GethTrace,
+};
+use alloy::transports::{Transport, TransportResult};
+
+use crate::storage::RAM_SLOT;
+use crate::Solchip8::Solchip8Instance;
+
+/// EVM opcodes listed in the report.
+const TOP_EVM_OPS: usize = 10;
+
+#[derive(Debug, Default, Clone, Copy)]
+struct FamilyStats {
+    ticks: u64,
+    gas: u64,
+    max: u64,
+}
+
+#[derive(Debug, Default, Clone, Copy)]
+struct SlotStats {
+    sload: u64,
+    sstore: u64,
+}
+
+#[derive(Debug, Default, Clone, Copy)]
+struct OpStats {
+    count: u64,
+    gas: u64,
+}
+
+#[derive(Debug, Default)]
+pub struct Profiler {
+    families: BTreeMap<&'static str, FamilyStats>,
+    slots: BTreeMap<U256, SlotStats>,
+    evm_ops: BTreeMap<String, OpStats>,
+}
+
+impl Profiler {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Trace the `tick()` transaction `tx`, which executed `op` and used `gas` in total.
+    pub async fn record<T, P, N>(
+        &mut self,
+        emu: &Solchip8Instance<T, P, N>,
+        op: u16,
+        tx: TxHash,
+        gas: u64,
+    ) -> TransportResult<()>
+    where
+        T: Transport + Clone,
+        P: Provider<T, N>,
+        N: Network,
+    {
+        let stats = self.families.entry(family(op)).or_default();
+        stats.ticks += 1;
+        stats.gas += gas;
+        stats.max = stats.max.max(gas);
+
+        let options = GethDebugTracingOptions {
+            config: GethDefaultTracingOptions::default()
+                .disable_storage()
+                .disable_memory()
+                .disable_return_data(),
+            ..Default::default()
+        };
+        let GethTrace::Default(frame) = emu.provider().debug_trace_transaction(tx, options).await?
+        else {
+            return Ok(());
+        };
+        for log in &frame.struct_logs {
+            let op_stats = self.evm_ops.entry(log.op.clone()).or_default();
+            op_stats.count += 1;
+            op_stats.gas += log.gas_cost;
+
+            // The slot is on top of the stack, which is the end of the list
+            let slot = log.stack.as_ref().and_then(|stack| stack.last());
+            match (log.op.as_str(), slot) {
+                ("SLOAD", Some(&slot)) => self.slots.entry(slot).or_default().sload += 1,
+                ("SSTORE", Some(&slot)) => self.slots.entry(slot).or_default().sstore += 1,
+                _ => {}
+            }
+        }
+        Ok(())
+    }
+}
+
+impl fmt::Display for Profiler {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let ticks: u64 = self.families.values().map(|stats| stats.ticks).sum();
+        let gas: u64 = self.families.values().map(|stats| stats.gas).sum();
+        writeln!(
+            f,
+            "profile: {} ticks, {} gas, {} per tick",
+            ticks,
+            gas,
+            gas.checked_div(ticks).unwrap_or(0)
+        )?;
+
+        let mut families: Vec<_> = self.families.iter().collect();
+        families.sort_by_key(|(_, stats)| std::cmp::Reverse(stats.gas));
+        writeln!(f)?;
+        writeln!(
+            f,
+            "{:<20} {:>7} {:>12} {:>10} {:>10} {:>6}",
+            "opcode family", "ticks", "gas", "mean", "max", "share"
+        )?;
+        for (name, stats) in families {
+            writeln!(
+                f,
+                "{:<20} {:>7} {:>12} {:>10} {:>10} {:>5.1}%",
+                name,
+                stats.ticks,
+                stats.gas,
+                stats.gas / stats.ticks,
+                stats.max,
+                100.0 * stats.gas as f64 / gas.max(1) as f64
+            )?;
+        }
+
+        let mut slots: Vec<_> = self.slots.iter().collect();
+        slots.sort_by_key(|(slot, stats)| (std::cmp::Reverse((stats.sstore, stats.sload)), **slot));
+        writeln!(f)?;
+        writeln!(f, "{:<32} {:>8} {:>8}", "storage slot", "SLOAD", "SSTORE")?;
+        for (slot, stats) in slots {
+            writeln!(
+                f,
+                "{:<32} {:>8} {:>8}",
+                slot_name(*slot),
+                stats.sload,
+                stats.sstore
+            )?;
+        }
+
+        let mut evm_ops: Vec<_> = self.evm_ops.iter().collect();
+        evm_ops.sort_by_key(|(_, stats)| std::cmp::Reverse(stats.gas));
+        writeln!(f)?;
+        write!(f, "{:<14} {:>10} {:>12}", "EVM opcode", "count", "gas")?;
+        for (op, stats) in evm_ops.into_iter().take(TOP_EVM_OPS) {
+            write!(f, "\n{:<14} {:>10} {:>12}", op, stats.count, stats.gas)?;
+        }
+        Ok(())
+    }
+}
+
+/// Name of a storage slot from the layout in `storage`, e.g. `emu.ram[0x200..0x21f]`.
+fn slot_name(slot: U256) -> String {
+    let Ok(slot) = usize::try_from(slot) else {
+        return format!("{slot:#x}");
+    };
+    match slot {
+        0..=2 => format!("FONTSET[{}..{}]", slot * 32, (slot * 32 + 31).min(79)),
+        3 => "emu.pc".to_string(),
+        4..=131 => {
+            let start = (slot - RAM_SLOT) * 32;
+            format!("emu.ram[{:#05x}..{:#05x}]", start, start + 31)
+        }
+        132..=139 => format!("emu.screen[{}]", slot - 132),
+        140 => "emu.v_reg".to_string(),
+        141 => "emu.i_reg, emu.sp".to_string(),
+        142 => "emu.stack".to_string(),
+        143 => "emu.keys, emu.dt, emu.st".to_string(),
+        144 => "emu.program_size".to_string(),
+        _ => format!("{slot:#x}"),
+    }
+}
+
+/// Opcode pattern and mnemonic an opcode belongs to, e.g. `DXYN DRW`, for grouping.
+fn family(op: u16) -> &'static str {
+    let n = op & 0x000F;
+    let nn = op & 0x00FF;
+
+    match (op & 0xF000) >> 12 {
+        0x0 if op == 0x0000 => "0000 NOP",
+        0x0 if op == 0x00E0 => "00E0 CLS",
+        0x0 if op == 0x00EE => "00EE RET",
+        0x1 => "1NNN JP",
+        0x2 => "2NNN CALL",
+        0x3 => "3XNN SE",
+        0x4 => "4XNN SNE",
+        0x5 if n == 0x0 => "5XY0 SE",
+        0x6 => "6XNN LD",
+        0x7 => "7XNN ADD",
+        0x8 => match n {
+            0x0 => "8XY0 LD",
+            0x1 => "8XY1 OR",
+            0x2 => "8XY2 AND",
+            0x3 => "8XY3 XOR",
+            0x4 => "8XY4 ADD",
+            0x5 => "8XY5 SUB",
+            0x6 => "8XY6 SHR",
+            0x7 => "8XY7 SUBN",
+            0xE => "8XYE SHL",
+            _ => "unknown",
+        },
+        0x9 if n == 0x0 => "9XY0 SNE",
+        0xA => "ANNN LD I",
+        0xB => "BNNN JP V0",
+        0xC => "CXNN RND",
+        0xD => "DXYN DRW",
+        0xE if nn == 0x9E => "EX9E SKP",
+        0xE if nn == 0xA1 => "EXA1 SKNP",
+        0xF => match nn {
+            0x07 => "FX07 LD Vx, DT",
+            0x0A => "FX0A LD Vx, K",
+            0x15 => "FX15 LD DT, Vx",
+            0x18 => "FX18 LD ST, Vx",
+            0x1E => "FX1E ADD I",
+            0x29 => "FX29 LD F",
+            0x33 => "FX33 LD B",
+            0x55 => "FX55 LD [I], Vx",
+            0x65 => "FX65 LD Vx, [I]",
+            _ => "unknown",
+        },
+        _ => "unknown",
+    }
+}
@@ -0,0 +1,29 @@
+//! `CXNN` random numbers computed off-chain, so the next one can be shown before it is drawn.
+//!
+//! The contract sets `VX = keccak256(abi.encodePacked(block.timestamp, blockhash(block.number -
+//! 1), pc)) % 256 & NN`, where `pc` has already moved past the `CXNN`. Packed, that is the
+//! 32-byte timestamp, the 32-byte parent hash and the 2-byte PC, and `% 256` is the last byte
+//! of the hash.
+
+use alloy::primitives::{keccak256, BlockHash};
+
+/// Byte the contract draws in a block with `timestamp` whose parent is `parent`, with `pc`
+/// as the contract holds it while executing the `CXNN`.
+pub fn random_byte(timestamp: u64, parent: BlockHash, pc: u16) -> u8 {
+    let mut packed = [0u8; 66];
+    packed[24..32].copy_from_slice(&timestamp.to_be_bytes());
+    packed[32..64].copy_from_slice(parent.as_slice());
+    packed[64..].copy_from_slice(&pc.to_be_bytes());
+    keccak256(packed)[31]
+}
+
+/// Register and value a `CXNN` at `addr` sets when run in the given block; `None` for any
+/// other opcode.
+pub fn predict(op: u16, addr: u16, timestamp: u64, parent: BlockHash) -> Option<(usize, u8)> {
+    if op & 0xF000 != 0xC000 {
+        return None;
+    }
+    let x = ((op & 0x0F00) >> 8) as usize;
+    let nn = (op & 0x00FF) as u8;
+    Some((x, random_byte(timestamp, parent, addr.wrapping_add(2)) & nn))
+}
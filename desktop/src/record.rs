@@ -0,0 +1,239 @@
+//! Gameplay recording: every frame read with `getDisplay()`, with the time it was captured,
+//! encoded as an animated GIF or an uncompressed Y4M stream.
+//!
+//! Frames are written as soon as the next one arrives, since that fixes how long each stays
+//! on screen. A GIF keeps the captured timing, rounded to its centisecond delays. Y4M has a
+//! fixed frame rate, so each output frame repeats whatever frame was on screen at its time.
+
+use std::fs::File;
+use std::io::{self, BufWriter, Write};
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use alloy::network::Network;
+use alloy::providers::Provider;
+use alloy::transports::Transport;
+use desktop::display::{self, PIXELS};
+
+use crate::screenshot::Style;
+use crate::Solchip8::Solchip8Instance;
+
+/// Shortest GIF delay viewers honour; shorter ones are often shown as 10cs.
+const MIN_GIF_DELAY: u64 = 2;
+
+/// Check that `path` names a format the recorder can write.
+pub fn parse_path(path: &str) -> Result<PathBuf, String> {
+    let path = PathBuf::from(path);
+    match path.extension().and_then(|ext| ext.to_str()) {
+        Some("gif" | "y4m") => Ok(path),
+        _ => Err("expected a `.gif` or `.y4m` file".to_string()),
+    }
+}
+
+enum Encoder {
+    Gif {
+        encoder: gif::Encoder<BufWriter<File>>,
+        /// Centiseconds of delay written so far.
+        written: u64,
+    },
+    Y4m {
+        out: BufWriter<File>,
+        fps: u32,
+        /// Output frames written so far.
+        written: u64,
+    },
+}
+
+pub struct Recorder {
+    encoder: Encoder,
+    style: Style,
+    dedup: bool,
+    /// Last frame, waiting for the next one to know how long it stays on screen.
+    pending: Option<[bool; PIXELS]>,
+    /// Frames kept, after deduplication.
+    frames: u64,
+}
+
+impl Recorder {
+    /// Start a recording in the format given by the extension of `path`; `fps` is only
+    /// used for Y4M.
+    pub fn create(path: &Path, style: Style, dedup: bool, fps: u32) -> io::Result<Self> {
+        let out = BufWriter::new(File::create(path)?);
+        let (width, height) = (style.width(), style.height());
+        let encoder = if path.extension().is_some_and(|ext| ext == "y4m") {
+            let mut out = out;
+            writeln!(out, "YUV4MPEG2 W{width} H{height} F{fps}:1 Ip A1:1 C444")?;
+            Encoder::Y4m {
+                out,
+                fps,
+                written: 0,
+            }
+        } else {
+            let palette = [style.palette.off, style.palette.on].concat();
+            let mut encoder = gif::Encoder::new(out, width as u16, height as u16, &palette)
+                .map_err(io::Error::other)?;
+            encoder
+                .set_repeat(gif::Repeat::Infinite)
+                .map_err(io::Error::other)?;
+            Encoder::Gif {
+                encoder,
+                written: 0,
+            }
+        };
+        Ok(Recorder {
+            encoder,
+            style,
+            dedup,
+            pending: None,
+            frames: 0,
+        })
+    }
+
+    /// Add the frame captured `at` since the recording started.
+    pub fn frame(&mut self, at: Duration, pixels: &[bool; PIXELS]) -> io::Result<()> {
+        if let Some(last) = self.pending {
+            if self.dedup && last == *pixels {
+                return Ok(());
+            }
+            if self.too_short(at) {
+                // Gone before a GIF can show it, the new frame takes its place
+                self.pending = Some(*pixels);
+                return Ok(());
+            }
+            self.write(&last, at)?;
+        }
+        self.pending = Some(*pixels);
+        self.frames += 1;
+        Ok(())
+    }
+
+    /// Whether the pending frame would end at `end` before the shortest GIF delay.
+    fn too_short(&self, end: Duration) -> bool {
+        match &self.encoder {
+            Encoder::Gif { written, .. } => centiseconds(end) < written + MIN_GIF_DELAY,
+            Encoder::Y4m { .. } => false,
+        }
+    }
+
+    /// Read the screen from the contract and add it as captured `at`.
+    pub async fn capture<T, P, N>(
+        &mut self,
+        emu: &Solchip8Instance<T, P, N>,
+        at: Duration,
+    ) -> io::Result<()>
+    where
+        T: Transport + Clone,
+        P: Provider<T, N>,
+        N: Network,
+    {
+        let screen = emu.getDisplay().call().await.map_err(io::Error::other)?._0;
+        self.frame(at, &display::decode(&screen))
+    }
+
+    /// Write the last frame, shown until `end`, and close the file. Returns the number of
+    /// frames kept.
+    pub fn finish(mut self, end: Duration) -> io::Result<u64> {
+        if let Some(last) = self.pending.take() {
+            self.write(&last, end)?;
+        }
+        match self.encoder {
+            Encoder::Gif { encoder, .. } => encoder.into_inner().map_err(io::Error::other)?,
+            Encoder::Y4m { out, .. } => out,
+        }
+        .flush()?;
+        Ok(self.frames)
+    }
+
+    /// Write `pixels` as on screen from the end of the previous frame until `end`.
+    fn write(&mut self, pixels: &[bool; PIXELS], end: Duration) -> io::Result<()> {
+        let indexed = self.style.indexed(pixels);
+        match &mut self.encoder {
+            Encoder::Gif { encoder, written } => {
+                // Delays add up to the captured time. Only the last frame can be shorter
+                // than the minimum, `frame` drops the others.
+                let delay = centiseconds(end)
+                    .saturating_sub(*written)
+                    .max(MIN_GIF_DELAY);
+                *written += delay;
+                let (width, height) = (self.style.width(), self.style.height());
+                let mut frame =
+                    gif::Frame::from_indexed_pixels(width as u16, height as u16, indexed, None);
+                frame.delay = delay as u16;
+                encoder.write_frame(&frame).map_err(io::Error::other)
+            }
+            Encoder::Y4m { out, fps, written } => {
+                let planes = yuv_planes(&self.style, &indexed);
+                while Duration::from_secs(*written) / *fps < end {
+                    out.write_all(b"FRAME\n")?;
+                    for plane in &planes {
+                        out.write_all(plane)?;
+                    }
+                    *written += 1;
+                }
+                Ok(())
+            }
+        }
+    }
+}
+
+/// Whole hundredths of a second in `time`, the unit of GIF delays.
+fn centiseconds(time: Duration) -> u64 {
+    (time.as_millis() / 10) as u64
+}
+
+/// Y, Cb and Cr planes of an indexed frame, full resolution (4:4:4), BT.601 studio range.
+fn yuv_planes(style: &Style, indexed: &[u8]) -> [Vec<u8>; 3] {
+    let yuv = |[r, g, b]: [u8; 3]| {
+        let (r, g, b) = (r as f64, g as f64, b as f64);
+        [
+            16.0 + (65.738 * r + 129.057 * g + 25.064 * b) / 256.0,
+            128.0 + (-37.945 * r - 74.494 * g + 112.439 * b) / 256.0,
+            128.0 + (112.439 * r - 94.154 * g - 18.285 * b) / 256.0,
+        ]
+        .map(|value| value.round() as u8)
+    };
+    let colours = [yuv(style.palette.off), yuv(style.palette.on)];
+    [0, 1, 2].map(|plane| {
+        indexed
+            .iter()
+            .map(|&index| colours[index as usize][plane])
+            .collect()
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use super::*;
+
+    #[test]
+    fn gif_delays_keep_up_with_short_frames() {
+        let path = std::env::temp_dir().join(format!("record-{}.gif", std::process::id()));
+        let mut recorder = Recorder::create(&path, Style::default(), false, 60).unwrap();
+        // A frame every 10ms is shorter than the 20ms a GIF can show
+        let mut pixels = [false; PIXELS];
+        for frame in 0..100 {
+            pixels[frame] = true;
+            recorder
+                .frame(Duration::from_millis(frame as u64 * 10), &pixels)
+                .unwrap();
+        }
+        let kept = recorder.finish(Duration::from_secs(1)).unwrap();
+
+        let mut decoder = gif::DecodeOptions::new()
+            .read_info(fs::File::open(&path).unwrap())
+            .unwrap();
+        let mut delays = Vec::new();
+        while let Some(frame) = decoder.read_next_frame().unwrap() {
+            delays.push(frame.delay as u64);
+        }
+        fs::remove_file(&path).unwrap();
+        assert_eq!(delays.len() as u64, kept);
+        assert!(
+            delays.iter().all(|&delay| delay >= MIN_GIF_DELAY),
+            "{delays:?}"
+        );
+        assert_eq!(delays.iter().sum::<u64>(), 100);
+    }
+}
@@ -0,0 +1,674 @@
+//! Replay files: the ROM, the state a run started from and the keys held during every tick,
+//! enough to send the same `keypress()`/`tick()` calls to another contract and check that
+//! the run ends the same way.
+//!
+//! A replay is one JSON document:
+//!
+//! ```text
+//! {
+//!   "version": 1,
+//!   "rom": "../c8games/PONG",
+//!   "rom_sha256": "…",
+//!   "initial": { "pc", "opcode", "v", "i", "sp", "stack", "dt", "st", "keys", "screen", "ram" },
+//!   "keys": [0, 0, 16, …],
+//!   "final": { … the same without "ram" },
+//!   "pinned": { "number", "hash", "timestamp" },
+//!   "font": { "name", "bytes" }
+//! }
+//! ```
+//!
+//! `keys` holds the key bitmask during each tick, in order. `CXNN` hashes the block
+//! timestamp and hash, so runs that draw random numbers only replay exactly when both were
+//! pinned with `determinism`; `pinned` is then the chain head before the first tick, which
+//! the replay has to reach as well. `font` is the `--font` the run was started with; the
+//! replay installs it at the same point, so the writes land on the same blocks.
+
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use alloy::hex;
+use alloy::network::Network;
+use alloy::primitives::U256;
+use alloy::providers::Provider;
+use alloy::transports::Transport;
+use desktop::client;
+use desktop::display::{self, PIXELS};
+use serde_json::{json, Value};
+
+use crate::determinism::{self, Head};
+use crate::font::Font;
+use crate::rom::{Rom, FONTSET_SIZE, RAM_SIZE};
+use crate::state::{Chip8State, NUM_REGS, STACK_SIZE};
+use crate::storage::read_ram;
+use crate::Solchip8::Solchip8Instance;
+
+const VERSION: u64 = 1;
+
+fn invalid(msg: String) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, msg)
+}
+
+/// Registers, screen and optionally all of RAM at one point of a run.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Snapshot {
+    pub state: Chip8State,
+    pub screen: [U256; 8],
+    pub ram: Option<Vec<u8>>,
+}
+
+impl Snapshot {
+    pub async fn fetch<T, P, N>(emu: &Solchip8Instance<T, P, N>, with_ram: bool) -> io::Result<Self>
+    where
+        T: Transport + Clone,
+        P: Provider<T, N>,
+        N: Network,
+    {
+        let state = Chip8State::fetch(emu).await.map_err(io::Error::other)?;
+        let screen = emu.getDisplay().call().await.map_err(io::Error::other)?._0;
+        let ram = match with_ram {
+            true => Some(read_ram(emu, 0, RAM_SIZE).await.map_err(io::Error::other)?),
+            false => None,
+        };
+        Ok(Snapshot { state, screen, ram })
+    }
+
+    fn to_json(&self) -> Value {
+        let state = &self.state;
+        let mut value = json!({
+            "pc": state.pc,
+            "opcode": state.opcode,
+            "v": state.v,
+            "i": state.i,
+            "sp": state.sp,
+            "stack": state.stack,
+            "dt": state.dt,
+            "st": state.st,
+            "keys": state.keys,
+            "screen": self.screen,
+        });
+        if let Some(ram) = &self.ram {
+            value["ram"] = json!(hex::encode(ram));
+        }
+        value
+    }
+
+    fn from_json(value: &Value) -> Result<Self, String> {
+        let int = |key: &str| {
+            value[key]
+                .as_u64()
+                .ok_or_else(|| format!("missing or invalid `{key}`"))
+        };
+        let ints = |key: &str, len: usize| {
+            let values = value[key]
+                .as_array()
+                .filter(|values| values.len() == len)
+                .ok_or_else(|| format!("`{key}` needs {len} values"))?;
+            values
+                .iter()
+                .map(|value| value.as_u64().ok_or(format!("invalid `{key}`")))
+                .collect::<Result<Vec<_>, _>>()
+        };
+        let mut state = Chip8State {
+            pc: int("pc")? as u16,
+            opcode: int("opcode")? as u16,
+            i: int("i")? as u16,
+            sp: int("sp")? as u16,
+            dt: int("dt")? as u8,
+            st: int("st")? as u8,
+            keys: int("keys")? as u16,
+            ..Default::default()
+        };
+        for (reg, v) in state.v.iter_mut().zip(ints("v", NUM_REGS)?) {
+            *reg = v as u8;
+        }
+        for (entry, value) in state.stack.iter_mut().zip(ints("stack", STACK_SIZE)?) {
+            *entry = value as u16;
+        }
+        let screen = serde_json::from_value(value["screen"].clone())
+            .map_err(|e| format!("invalid `screen`: {e}"))?;
+        let ram = match value["ram"].as_str() {
+            Some(ram) => {
+                let ram = hex::decode(ram).map_err(|e| format!("invalid `ram`: {e}"))?;
+                if ram.len() != RAM_SIZE {
+                    return Err(format!("`ram` needs {RAM_SIZE} bytes"));
+                }
+                Some(ram)
+            }
+            None => None,
+        };
+        Ok(Snapshot { state, screen, ram })
+    }
+
+    /// Names of the fields that differ, leaving out RAM.
+    fn differences(&self, other: &Snapshot) -> Vec<&'static str> {
+        let (a, b) = (&self.state, &other.state);
+        let fields = [
+            ("pc", a.pc != b.pc),
+            ("opcode", a.opcode != b.opcode),
+            ("v", a.v != b.v),
+            ("i", a.i != b.i),
+            ("sp", a.sp != b.sp),
+            ("stack", a.stack != b.stack),
+            ("dt", a.dt != b.dt),
+            ("st", a.st != b.st),
+            ("keys", a.keys != b.keys),
+            ("screen", self.screen != other.screen),
+        ];
+        fields
+            .into_iter()
+            .filter(|(_, differs)| *differs)
+            .map(|(name, _)| name)
+            .collect()
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Replay {
+    /// ROM path as given when recording.
+    pub rom: PathBuf,
+    pub rom_sha256: String,
+    /// State before the first tick, with RAM.
+    pub initial: Snapshot,
+    /// Key bitmask during each tick.
+    pub keys: Vec<u16>,
+    /// State after the last tick.
+    pub end: Snapshot,
+    /// Chain head before the first tick, if blocks were pinned.
+    pub pinned: Option<Head>,
+    /// Font written over the built-in one before recording.
+    pub font: Option<Font>,
+}
+
+impl Replay {
+    pub fn load(path: &Path) -> io::Result<Self> {
+        let value: Value =
+            serde_json::from_str(&fs::read_to_string(path)?).map_err(|e| invalid(e.to_string()))?;
+        let version = value["version"].as_u64();
+        if version != Some(VERSION) {
+            return Err(invalid(format!("unsupported replay version {version:?}")));
+        }
+        let keys = value["keys"]
+            .as_array()
+            .ok_or_else(|| invalid("missing `keys`".into()))?
+            .iter()
+            .map(|keys| keys.as_u64().map(|keys| keys as u16))
+            .collect::<Option<Vec<_>>>()
+            .ok_or_else(|| invalid("invalid `keys`".into()))?;
+        let snapshot = |key: &str| {
+            Snapshot::from_json(&value[key]).map_err(|e| invalid(format!("`{key}`: {e}")))
+        };
+        let initial = snapshot("initial")?;
+        if initial.ram.is_none() {
+            return Err(invalid("`initial` needs `ram`".into()));
+        }
+        let pinned = match &value["pinned"] {
+            Value::Null => None,
+            head => Some(head_from_json(head).map_err(|e| invalid(format!("`pinned`: {e}")))?),
+        };
+        let font = match &value["font"] {
+            Value::Null => None,
+            font => Some(font_from_json(font).map_err(|e| invalid(format!("`font`: {e}")))?),
+        };
+        Ok(Replay {
+            rom: value["rom"].as_str().unwrap_or_default().into(),
+            rom_sha256: value["rom_sha256"].as_str().unwrap_or_default().to_string(),
+            initial,
+            keys,
+            end: snapshot("final")?,
+            pinned,
+            font,
+        })
+    }
+
+    pub fn save(&self, path: &Path) -> io::Result<()> {
+        let mut value = json!({
+            "version": VERSION,
+            "rom": self.rom,
+            "rom_sha256": self.rom_sha256,
+            "initial": self.initial.to_json(),
+            "keys": self.keys,
+            "final": self.end.to_json(),
+        });
+        if let Some(head) = &self.pinned {
+            value["pinned"] = json!({
+                "number": head.number,
+                "hash": head.hash,
+                "timestamp": head.timestamp,
+            });
+        }
+        if let Some(font) = &self.font {
+            value["font"] = json!({
+                "name": font.name,
+                "bytes": hex::encode(font.bytes),
+            });
+        }
+        fs::write(path, format!("{value:#}\n"))
+    }
+}
+
+/// Builds a replay while a run goes on, written out when it ends.
+pub struct Recording {
+    path: PathBuf,
+    rom: PathBuf,
+    rom_sha256: String,
+    initial: Snapshot,
+    keys: Vec<u16>,
+    pinned: Option<Head>,
+    font: Option<Font>,
+}
+
+impl Recording {
+    /// Start recording the run of `rom`, loaded in `emu` with `font`, from its current state.
+    /// `pinned` says whether the node's blocks were pinned with `determinism::pin`.
+    pub async fn start<T, P, N>(
+        emu: &Solchip8Instance<T, P, N>,
+        rom: &Path,
+        font: Option<&Font>,
+        path: &Path,
+        pinned: bool,
+    ) -> io::Result<Self>
+    where
+        T: Transport + Clone,
+        P: Provider<T, N>,
+        N: Network,
+    {
+        let rom_sha256 = Rom::from_file(rom)
+            .map_err(|e| invalid(e.to_string()))?
+            .sha256();
+        let pinned = match pinned {
+            true => Some(
+                determinism::head(emu.provider())
+                    .await
+                    .map_err(io::Error::other)?,
+            ),
+            false => None,
+        };
+        Ok(Recording {
+            path: path.to_path_buf(),
+            rom: rom.to_path_buf(),
+            rom_sha256,
+            initial: Snapshot::fetch(emu, true).await?,
+            keys: Vec::new(),
+            pinned,
+            font: font.cloned(),
+        })
+    }
+
+    /// Record the keys of a `tick()` that went through. `tick()` leaves the keys alone, so
+    /// they are read after it.
+    pub async fn tick<T, P, N>(&mut self, emu: &Solchip8Instance<T, P, N>) -> io::Result<()>
+    where
+        T: Transport + Clone,
+        P: Provider<T, N>,
+        N: Network,
+    {
+        let keys = emu.getKeys().call().await.map_err(io::Error::other)?._0;
+        self.keys.push(keys);
+        Ok(())
+    }
+
+    /// Take the final state and write the replay. Returns the number of ticks recorded.
+    pub async fn finish<T, P, N>(self, emu: &Solchip8Instance<T, P, N>) -> io::Result<usize>
+    where
+        T: Transport + Clone,
+        P: Provider<T, N>,
+        N: Network,
+    {
+        let replay = Replay {
+            rom: self.rom,
+            rom_sha256: self.rom_sha256,
+            initial: self.initial,
+            keys: self.keys,
+            end: Snapshot::fetch(emu, false).await?,
+            pinned: self.pinned,
+            font: self.font,
+        };
+        replay.save(&self.path)?;
+        Ok(replay.keys.len())
+    }
+}
+
+/// Play `replay` on `emu`, which has its ROM loaded, and report whether the display and
+/// registers end as recorded.
+pub async fn play<T, P, N>(emu: &Solchip8Instance<T, P, N>, replay: &Replay) -> io::Result<bool>
+where
+    T: Transport + Clone,
+    P: Provider<T, N>,
+    N: Network,
+{
+    let writes = restore(emu, &replay.initial).await?;
+    println!("initial state restored, {writes} values written");
+    if let Some(recorded) = &replay.pinned {
+        let head = determinism::head(emu.provider())
+            .await
+            .map_err(io::Error::other)?;
+        if head != *recorded {
+            println!("⚠️ recorded from {recorded}");
+            println!("⚠️ replaying from {head}");
+            println!("⚠️ the chain differs, so CXNN will draw different numbers");
+        }
+    }
+
+    let mut keys = replay.initial.state.keys;
+    for (tick, &held) in replay.keys.iter().enumerate() {
+        send_keys(emu, keys, held).await.map_err(io::Error::other)?;
+        keys = held;
+        if let Err(e) = client::transact(emu.tick()).await {
+            println!("tick {tick} reverted: {e}");
+            println!(
+                "replay diverged, the recording ran {} ticks",
+                replay.keys.len()
+            );
+            return Ok(false);
+        }
+    }
+
+    let end = Snapshot::fetch(emu, false).await?;
+    let fields = end.differences(&replay.end);
+    if fields.is_empty() {
+        println!("replay matches after {} ticks", replay.keys.len());
+        return Ok(true);
+    }
+    println!(
+        "replay diverged after {} ticks ({})",
+        replay.keys.len(),
+        fields.join(", ")
+    );
+    println!("recorded:\n{}", replay.end.state);
+    println!("replayed:\n{}", end.state);
+    if fields.contains(&"screen") {
+        let (recorded, replayed) = (
+            display::decode(&replay.end.screen),
+            display::decode(&end.screen),
+        );
+        let differing = (0..PIXELS).filter(|&p| recorded[p] != replayed[p]).count();
+        println!("{differing} pixels differ");
+    }
+    Ok(false)
+}
+
+/// Write every value of `initial` that differs from the contract's state. Returns the number
+/// of writes.
+async fn restore<T, P, N>(emu: &Solchip8Instance<T, P, N>, initial: &Snapshot) -> io::Result<usize>
+where
+    T: Transport + Clone,
+    P: Provider<T, N>,
+    N: Network,
+{
+    let current = Snapshot::fetch(emu, true).await?;
+    let (want, have) = (&initial.state, &current.state);
+    if want.pc != have.pc {
+        return Err(io::Error::other(format!(
+            "the recording starts at PC {:#05x}, the contract at {:#05x}, and PC cannot be set",
+            want.pc, have.pc
+        )));
+    }
+
+    write_differences(emu, initial, &current)
+        .await
+        .map_err(io::Error::other)
+}
+
+async fn write_differences<T, P, N>(
+    emu: &Solchip8Instance<T, P, N>,
+    initial: &Snapshot,
+    current: &Snapshot,
+) -> Result<usize, alloy::contract::Error>
+where
+    T: Transport + Clone,
+    P: Provider<T, N>,
+    N: Network,
+{
+    let (want, have) = (&initial.state, &current.state);
+    let mut writes = 0;
+    for (idx, (&v, &old)) in want.v.iter().zip(&have.v).enumerate() {
+        if v != old {
+            client::transact(emu.setVRegister(idx as u8, v)).await?;
+            writes += 1;
+        }
+    }
+    if want.i != have.i {
+        client::transact(emu.setIRegister(want.i)).await?;
+        writes += 1;
+    }
+    if want.sp != have.sp {
+        client::transact(emu.setSP(want.sp)).await?;
+        writes += 1;
+    }
+    for (idx, (&value, &old)) in want.stack.iter().zip(&have.stack).enumerate() {
+        if value != old {
+            client::transact(emu.setStackValue(U256::from(idx), value)).await?;
+            writes += 1;
+        }
+    }
+    if want.dt != have.dt {
+        client::transact(emu.setDelayTimer(want.dt)).await?;
+        writes += 1;
+    }
+    if want.st != have.st {
+        client::transact(emu.setSoundTimer(want.st)).await?;
+        writes += 1;
+    }
+    writes += send_keys(emu, have.keys, want.keys).await?;
+    let ram = initial.ram.as_deref().unwrap_or_default();
+    let old_ram = current.ram.as_deref().unwrap_or_default();
+    for (addr, (&byte, &old)) in ram.iter().zip(old_ram).enumerate() {
+        if byte != old {
+            client::transact(emu.setRAMValueAt(U256::from(addr), byte)).await?;
+            writes += 1;
+        }
+    }
+    let (screen, old_screen) = (
+        display::decode(&initial.screen),
+        display::decode(&current.screen),
+    );
+    for pixel in (0..PIXELS).filter(|&p| screen[p] != old_screen[p]) {
+        // setScreenPixel counts bits from the low end of each word, decode from the high end
+        let index = pixel / 256 * 256 + 255 - pixel % 256;
+        client::transact(emu.setScreenPixel(U256::from(index), screen[pixel])).await?;
+        writes += 1;
+    }
+    Ok(writes)
+}
+
+/// Send the `keypress()` calls that turn the key bitmask `from` into `to`, lowest key first.
+/// Returns the number sent.
+///
+/// Runs that should build the same blocks, such as a pinned session and its replay, must send
+/// key changes this way.
+pub async fn send_keys<T, P, N>(
+    emu: &Solchip8Instance<T, P, N>,
+    from: u16,
+    to: u16,
+) -> Result<usize, alloy::contract::Error>
+where
+    T: Transport + Clone,
+    P: Provider<T, N>,
+    N: Network,
+{
+    let changed: Vec<usize> = (0..16).filter(|k| (from ^ to) & (1 << k) != 0).collect();
+    for &k in &changed {
+        client::transact(emu.keypress(U256::from(k), to & (1 << k) != 0)).await?;
+    }
+    Ok(changed.len())
+}
+
+fn head_from_json(value: &Value) -> Result<Head, String> {
+    let int = |key: &str| {
+        value[key]
+            .as_u64()
+            .ok_or_else(|| format!("missing or invalid `{key}`"))
+    };
+    let hash = value["hash"]
+        .as_str()
+        .and_then(|hash| hash.parse().ok())
+        .ok_or("missing or invalid `hash`")?;
+    Ok(Head {
+        number: int("number")?,
+        hash,
+        timestamp: int("timestamp")?,
+    })
+}
+
+fn font_from_json(value: &Value) -> Result<Font, String> {
+    let name = value["name"].as_str().ok_or("missing or invalid `name`")?;
+    let bytes = value["bytes"]
+        .as_str()
+        .ok_or("missing or invalid `bytes`")?;
+    let bytes = hex::decode(bytes).map_err(|e| format!("invalid `bytes`: {e}"))?;
+    let bytes = bytes
+        .try_into()
+        .map_err(|_| format!("`bytes` needs {FONTSET_SIZE} bytes"))?;
+    Ok(Font {
+        name: name.to_string(),
+        bytes,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use alloy::primitives::BlockHash;
+
+    use super::*;
+
+    fn snapshot(with_ram: bool) -> Snapshot {
+        let mut state = Chip8State {
+            pc: 0x2A4,
+            opcode: 0xD015,
+            i: 0x3F0,
+            sp: 1,
+            dt: 12,
+            st: 3,
+            keys: 0x0210,
+            ..Default::default()
+        };
+        state.v[0] = 0x3C;
+        state.v[15] = 1;
+        state.stack[0] = 0x208;
+        let mut screen = [U256::ZERO; 8];
+        screen[0] = U256::from(0xF0u64) << 248;
+        screen[7] = U256::MAX;
+        let ram = with_ram.then(|| (0..RAM_SIZE).map(|addr| addr as u8).collect());
+        Snapshot { state, screen, ram }
+    }
+
+    /// A replay file in the temp dir, removed when dropped.
+    struct TempReplay(PathBuf);
+
+    impl TempReplay {
+        fn new(name: &str) -> Self {
+            let path = std::env::temp_dir().join(format!("solchip8-{}-{name}", std::process::id()));
+            TempReplay(path)
+        }
+
+        fn load(&self, contents: &Value) -> String {
+            fs::write(&self.0, contents.to_string()).unwrap();
+            Replay::load(&self.0).unwrap_err().to_string()
+        }
+    }
+
+    impl Drop for TempReplay {
+        fn drop(&mut self) {
+            let _ = fs::remove_file(&self.0);
+        }
+    }
+
+    #[test]
+    fn snapshots_round_trip_through_json() {
+        for with_ram in [false, true] {
+            let snapshot = snapshot(with_ram);
+            assert_eq!(Snapshot::from_json(&snapshot.to_json()), Ok(snapshot));
+        }
+    }
+
+    #[test]
+    fn invalid_snapshots_name_the_field() {
+        let valid = snapshot(true).to_json();
+        let broken = |key: &str, value: Value| {
+            let mut json = valid.clone();
+            json[key] = value;
+            Snapshot::from_json(&json).unwrap_err()
+        };
+        assert_eq!(broken("pc", Value::Null), "missing or invalid `pc`");
+        assert_eq!(broken("v", json!([1, 2, 3])), "`v` needs 16 values");
+        assert_eq!(
+            broken("stack", json!([0; 16].map(|_| "x"))),
+            "invalid `stack`"
+        );
+        assert!(broken("screen", json!([1])).starts_with("invalid `screen`"));
+        assert_eq!(broken("ram", json!("00ff")), "`ram` needs 4096 bytes");
+        assert!(broken("ram", json!("xyz")).starts_with("invalid `ram`"));
+    }
+
+    #[test]
+    fn replays_round_trip_through_files() {
+        let file = TempReplay::new("round-trip.json");
+        let replay = Replay {
+            rom: "../c8games/PONG".into(),
+            rom_sha256: "1db3".to_string(),
+            initial: snapshot(true),
+            keys: vec![0, 0x10, 0x10, 0],
+            end: snapshot(false),
+            pinned: Some(Head {
+                number: 7,
+                hash: BlockHash::repeat_byte(0xAB),
+                timestamp: 1_700_000_007,
+            }),
+            font: Font::builtin("vip"),
+        };
+        replay.save(&file.0).unwrap();
+        let loaded = Replay::load(&file.0).unwrap();
+        assert_eq!(loaded.rom, replay.rom);
+        assert_eq!(loaded.rom_sha256, replay.rom_sha256);
+        assert_eq!(loaded.initial, replay.initial);
+        assert_eq!(loaded.keys, replay.keys);
+        assert_eq!(loaded.end, replay.end);
+        assert_eq!(loaded.pinned, replay.pinned);
+        let (font, recorded) = (loaded.font.unwrap(), replay.font.unwrap());
+        assert_eq!((font.name, font.bytes), (recorded.name, recorded.bytes));
+    }
+
+    #[test]
+    fn invalid_replays_are_rejected() {
+        let file = TempReplay::new("invalid.json");
+        let valid = json!({
+            "version": VERSION,
+            "rom": "ROM",
+            "rom_sha256": "",
+            "initial": snapshot(true).to_json(),
+            "keys": [0, 1],
+            "final": snapshot(false).to_json(),
+        });
+        let broken = |key: &str, value: Value| {
+            let mut json = valid.clone();
+            json[key] = value;
+            file.load(&json)
+        };
+        assert_eq!(
+            broken("version", json!(2)),
+            "unsupported replay version Some(2)"
+        );
+        assert_eq!(broken("keys", Value::Null), "missing `keys`");
+        assert_eq!(broken("keys", json!([0, "x"])), "invalid `keys`");
+        assert_eq!(
+            broken("initial", snapshot(false).to_json()),
+            "`initial` needs `ram`"
+        );
+        assert_eq!(
+            broken("final", json!({})),
+            "`final`: missing or invalid `pc`"
+        );
+        assert_eq!(
+            broken("pinned", json!({ "number": 1, "timestamp": 2 })),
+            "`pinned`: missing or invalid `hash`"
+        );
+        assert_eq!(
+            broken("font", json!({ "name": "vip", "bytes": "f0" })),
+            "`font`: `bytes` needs 80 bytes"
+        );
+
+        fs::write(&file.0, "{").unwrap();
+        assert!(Replay::load(&file.0).is_err());
+    }
+}
@@ -0,0 +1,232 @@
+//! ROM validation and metadata, checked before anything is sent to the contract.
+
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::Path;
+
+use alloy::hex;
+use alloy::network::Network;
+use alloy::providers::Provider;
+use alloy::transports::Transport;
+use sha1::Sha1;
+use sha2::{Digest, Sha256};
+
+use crate::storage;
+use crate::Solchip8::Solchip8Instance;
+
+/// Size of the contract's RAM in bytes.
+pub const RAM_SIZE: usize = 4096;
+/// Address the contract loads programs to.
+pub const START_ADDR: usize = 0x200;
+/// Largest program `load()` accepts (`RAM_SIZE - START_ADDR`).
+pub const MAX_ROM_SIZE: usize = RAM_SIZE - START_ADDR;
+/// Size of the font the contract keeps at the start of RAM.
+pub const FONTSET_SIZE: usize = 80;
+
+/// SHA-256 digests of the ROMs bundled in `c8games`, with their titles.
+const CATALOG: &[(&str, &str)] = &[
+    ("15ce3e542f758840d2b4fb0161a2bc3f0e4947d29816ea2ea32c7b13a79b7039", "15 Puzzle"),
+    ("22ca535175f53fd0c8c0295b77198d7830a9c44b81497f14ee1fbc6c1322adc0", "Blinky"),
+    ("e54d22df013a1db0681a7b587beafc574f3bdcb2b23f8563f81b7be9d58b37e0", "Blitz"),
+    ("c435e310ed832846a10f6d19e103910400a97dce27745370cb18207f24baee39", "Brix"),
+    ("871349b9cac53b5f99aabd3e25a71ad9979b85f1e7664049ad62fe288d1a0557", "Connect 4"),
+    ("9f5175a62e9ffb77f150e494e77f525a73800f54d569cf3455bf7c2264ffc922", "Guess"),
+    ("4f0b0ea0ca8cb819574dd1bef22943dd04282e005647f9dcfd9246d4e2458a89", "Hidden"),
+    ("2d0e1fa53216b297e74041d4fb766f42327a42893e83bb4ec931a9dff5c2dd10", "Space Invaders"),
+    ("ff3139e8ce77c2bdad54d386fa17825466778885abd1fb2fd5f9af4c6aa639f5", "Kaleidoscope"),
+    ("86437986e84b5c944f8883547b4380cbdaacb08503bf1cb65f7167782f786060", "Maze"),
+    ("1a684bdb74e4c34cdc74aa92eb6bf61e719b2885e8e08e7bdd7644f9e4c07460", "Merlin"),
+    ("70fde31eb67c3b405b7484be49c4685a4de2de4a85194784dcb39c3aed4013fb", "Missile Command"),
+    ("1db31d734b9352f96aa5e11d9a3085b043a04f21cc793ac9bfde62f857f983e9", "Pong"),
+    ("380d62da4bd05464dd3a73112cdfbf1ab9f2c78f3984103f6f6ccc0c5c76562f", "Pong 2"),
+    ("e5582b76ad9d9b37a8b55e5456c7d9de1d04159e3eb05d4449f117abb8eba080", "Puzzle"),
+    ("8e09b5a0181774546bb6b21b7bc02461cabf1f57670be30d4d7ec207a6d480f3", "Syzygy"),
+    ("48206f279f572b908e2599d81d1aaaffdd61b2d576f805a79cb447bf476c539d", "Tank"),
+    ("667cb026dee03f59f3a2fd81a2ffeab47da87731883f9601d37ba019976f94dd", "Tetris"),
+    ("4a07eed424eb5bbea779386f1c600f61ec7f6125539f64e4073cae2aeba7c039", "Tic-Tac-Toe"),
+    ("281d3bcc61227e15a5d3294b0e10facc156ec1bd819a3018d92e3ccf3a07acf1", "UFO"),
+    ("c4f452abdd1a6a31a5ee3726fad52eea085f27c29ea28307d38a4ebf08d60278", "Vertical Brix"),
+    ("78fdc4cceb3942bcfcebe75de9f3651906bd3a968cd1f9c24b6bebe65a10ceea", "Vers"),
+    ("4304cafe94cc85802ec52b330f7ab3dcd7aee3a91b2c653aa441aad3cc741420", "Wipe Off"),
+];
+
+#[derive(Debug)]
+pub enum RomError {
+    Io(io::Error),
+    Empty,
+    TooLarge(usize),
+    Contract(alloy::contract::Error),
+    Mismatch { addr: usize, expected: u8, found: u8 },
+}
+
+impl fmt::Display for RomError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            RomError::Io(e) => write!(f, "unable to read ROM: {e}"),
+            RomError::Empty => write!(f, "ROM is empty"),
+            RomError::TooLarge(size) => write!(
+                f,
+                "ROM is {size} bytes, at most {MAX_ROM_SIZE} bytes fit after {START_ADDR:#05x}"
+            ),
+            RomError::Contract(e) => write!(f, "unable to read contract RAM: {e}"),
+            RomError::Mismatch {
+                addr,
+                expected,
+                found,
+            } => write!(
+                f,
+                "RAM mismatch at {addr:#05x}: expected {expected:#04x}, found {found:#04x}"
+            ),
+        }
+    }
+}
+
+impl std::error::Error for RomError {}
+
+impl From<io::Error> for RomError {
+    fn from(e: io::Error) -> Self {
+        RomError::Io(e)
+    }
+}
+
+impl From<alloy::contract::Error> for RomError {
+    fn from(e: alloy::contract::Error) -> Self {
+        RomError::Contract(e)
+    }
+}
+
+/// A validated CHIP-8 program.
+#[derive(Debug, Clone)]
+pub struct Rom {
+    data: Vec<u8>,
+    sha256: [u8; 32],
+    sha1: [u8; 20],
+    title: Option<&'static str>,
+}
+
+impl Rom {
+    pub fn from_file(path: impl AsRef<Path>) -> Result<Self, RomError> {
+        Self::from_bytes(fs::read(path)?)
+    }
+
+    pub fn from_bytes(data: Vec<u8>) -> Result<Self, RomError> {
+        if data.is_empty() {
+            return Err(RomError::Empty);
+        }
+        if data.len() > MAX_ROM_SIZE {
+            return Err(RomError::TooLarge(data.len()));
+        }
+
+        let sha256: [u8; 32] = Sha256::digest(&data).into();
+        let sha1: [u8; 20] = Sha1::digest(&data).into();
+        let digest = hex::encode(sha256);
+        let title = CATALOG
+            .iter()
+            .find(|(hash, _)| *hash == digest)
+            .map(|(_, title)| *title);
+
+        Ok(Self {
+            data,
+            sha256,
+            sha1,
+            title,
+        })
+    }
+
+    pub fn bytes(&self) -> &[u8] {
+        &self.data
+    }
+
+    /// SHA-256 of the program, as hex.
+    pub fn sha256(&self) -> String {
+        hex::encode(self.sha256)
+    }
+
+    /// Title from the catalog of bundled games, if the ROM is one of them.
+    pub fn title(&self) -> Option<&'static str> {
+        self.title
+    }
+
+    /// Send the program to `load()` and check it landed in RAM.
+    pub async fn load<T, P, N>(&self, emu: &Solchip8Instance<T, P, N>) -> Result<(), RomError>
+    where
+        T: Transport + Clone,
+        P: Provider<T, N>,
+        N: Network,
+    {
+        let builder = emu.load(self.bytes().to_vec());
+        builder.call().await?;
+        // 250ms
+        let tx = builder
+            .send()
+            .await?
+            .get_receipt()
+            .await
+            .map_err(alloy::contract::Error::from)?;
+        println!("load tx: {:?}", tx);
+        self.verify(emu).await
+    }
+
+    /// Check every byte of the program against contract RAM from `START_ADDR`.
+    pub async fn verify<T, P, N>(&self, emu: &Solchip8Instance<T, P, N>) -> Result<(), RomError>
+    where
+        T: Transport + Clone,
+        P: Provider<T, N>,
+        N: Network,
+    {
+        let ram = storage::read_ram(emu, START_ADDR, self.data.len())
+            .await
+            .map_err(alloy::contract::Error::from)?;
+        for (i, (&expected, &found)) in self.data.iter().zip(&ram).enumerate() {
+            if found != expected {
+                return Err(RomError::Mismatch {
+                    addr: START_ADDR + i,
+                    expected,
+                    found,
+                });
+            }
+        }
+        Ok(())
+    }
+}
+
+impl fmt::Display for Rom {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} ({} bytes)\n  sha256: {}\n  sha1:   {}",
+            self.title.unwrap_or("unknown ROM"),
+            self.data.len(),
+            hex::encode(self.sha256),
+            hex::encode(self.sha1)
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn empty_and_oversized_roms_are_rejected() {
+        assert!(matches!(Rom::from_bytes(Vec::new()), Err(RomError::Empty)));
+        assert!(matches!(
+            Rom::from_bytes(vec![0; MAX_ROM_SIZE + 1]),
+            Err(RomError::TooLarge(3585))
+        ));
+        let largest = Rom::from_bytes(vec![0; MAX_ROM_SIZE]).unwrap();
+        assert_eq!(largest.bytes().len(), 3584);
+    }
+
+    #[test]
+    fn bundled_roms_are_found_by_sha256() {
+        let pong = Rom::from_bytes(include_bytes!("../../c8games/PONG").to_vec()).unwrap();
+        assert_eq!(pong.title(), Some("Pong"));
+        assert_eq!(pong.sha256(), CATALOG[12].0);
+
+        let mut patched = pong.bytes().to_vec();
+        patched[0] ^= 1;
+        assert_eq!(Rom::from_bytes(patched).unwrap().title(), None);
+    }
+}
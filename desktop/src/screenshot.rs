@@ -0,0 +1,230 @@
+//! Screenshots of the contract's screen as PNG or plain PBM.
+//!
+//! Both encodings depend on nothing but the pixels, the scale and the palette, so the same
+//! frame always gives the same bytes and the files can be committed as golden images.
+
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::Path;
+use std::str::FromStr;
+
+use alloy::network::Network;
+use alloy::providers::Provider;
+use alloy::transports::Transport;
+use desktop::display::{self, PIXELS};
+use desktop::{SCREEN_HEIGHT, SCREEN_WIDTH};
+
+use crate::Solchip8::Solchip8Instance;
+
+/// Colours of unlit and lit pixels.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Palette {
+    pub off: [u8; 3],
+    pub on: [u8; 3],
+}
+
+impl Default for Palette {
+    /// White on black, as in the window.
+    fn default() -> Self {
+        Palette {
+            off: [0x00; 3],
+            on: [0xFF; 3],
+        }
+    }
+}
+
+impl FromStr for Palette {
+    type Err = String;
+
+    /// `RRGGBB,RRGGBB`, off colour first, each optionally starting with `#`.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let colour = |s: &str| {
+            let hex = s.trim().trim_start_matches('#');
+            let value = u32::from_str_radix(hex, 16)
+                .ok()
+                .filter(|_| hex.len() == 6)
+                .ok_or(format!("invalid colour `{s}`, expected RRGGBB"))?;
+            let [_, r, g, b] = value.to_be_bytes();
+            Ok::<_, String>([r, g, b])
+        };
+        let (off, on) = s
+            .split_once(',')
+            .ok_or("expected `RRGGBB,RRGGBB`, off colour first")?;
+        Ok(Palette {
+            off: colour(off)?,
+            on: colour(on)?,
+        })
+    }
+}
+
+impl fmt::Display for Palette {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let [r, g, b] = self.off;
+        write!(f, "{r:02x}{g:02x}{b:02x},")?;
+        let [r, g, b] = self.on;
+        write!(f, "{r:02x}{g:02x}{b:02x}")
+    }
+}
+
+/// How a frame is turned into an image.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Style {
+    /// Image pixels per CHIP-8 pixel, in each direction.
+    pub scale: u32,
+    pub palette: Palette,
+}
+
+impl Default for Style {
+    fn default() -> Self {
+        Style {
+            scale: 10,
+            palette: Palette::default(),
+        }
+    }
+}
+
+impl Style {
+    pub fn width(&self) -> u32 {
+        SCREEN_WIDTH as u32 * self.scale
+    }
+
+    pub fn height(&self) -> u32 {
+        SCREEN_HEIGHT as u32 * self.scale
+    }
+
+    /// The frame scaled up, one byte per image pixel: 0 off, 1 on.
+    pub fn indexed(&self, pixels: &[bool; PIXELS]) -> Vec<u8> {
+        let scale = self.scale as usize;
+        let mut out = Vec::with_capacity(PIXELS * scale * scale);
+        for row in pixels.chunks(SCREEN_WIDTH) {
+            let line: Vec<u8> = row
+                .iter()
+                .flat_map(|&on| std::iter::repeat_n(on as u8, scale))
+                .collect();
+            for _ in 0..scale {
+                out.extend_from_slice(&line);
+            }
+        }
+        out
+    }
+}
+
+/// Indexed-colour PNG of the frame.
+pub fn to_png(pixels: &[bool; PIXELS], style: &Style) -> io::Result<Vec<u8>> {
+    let mut out = Vec::new();
+    let mut encoder = png::Encoder::new(&mut out, style.width(), style.height());
+    encoder.set_color(png::ColorType::Indexed);
+    encoder.set_depth(png::BitDepth::Eight);
+    encoder.set_palette([style.palette.off, style.palette.on].concat());
+    let mut writer = encoder.write_header().map_err(io::Error::other)?;
+    writer
+        .write_image_data(&style.indexed(pixels))
+        .map_err(io::Error::other)?;
+    writer.finish().map_err(io::Error::other)?;
+    Ok(out)
+}
+
+/// Write the frame to `path`, as plain PBM if it ends in `.pbm` and PNG otherwise.
+///
+/// PBM is always one character per CHIP-8 pixel; the style only applies to PNG.
+pub fn save(path: &Path, pixels: &[bool; PIXELS], style: &Style) -> io::Result<()> {
+    match path.extension() {
+        Some(ext) if ext == "pbm" => fs::write(path, display::to_pbm(pixels)),
+        _ => fs::write(path, to_png(pixels, style)?),
+    }
+}
+
+/// Read the screen from the contract and save it to `path`.
+pub async fn capture<T, P, N>(
+    emu: &Solchip8Instance<T, P, N>,
+    path: &Path,
+    style: &Style,
+) -> io::Result<()>
+where
+    T: Transport + Clone,
+    P: Provider<T, N>,
+    N: Network,
+{
+    let screen = emu.getDisplay().call().await.map_err(io::Error::other)?._0;
+    save(path, &display::decode(&screen), style)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A frame with the top-left and bottom-right pixels lit.
+    fn corners() -> [bool; PIXELS] {
+        let mut pixels = [false; PIXELS];
+        pixels[0] = true;
+        pixels[PIXELS - 1] = true;
+        pixels
+    }
+
+    #[test]
+    fn palettes_parse_from_hex() {
+        let palette: Palette = "#0f380f, 9bbc0f".parse().unwrap();
+        assert_eq!(palette.off, [0x0F, 0x38, 0x0F]);
+        assert_eq!(palette.on, [0x9B, 0xBC, 0x0F]);
+        assert_eq!(palette.to_string(), "0f380f,9bbc0f");
+        assert_eq!(palette.to_string().parse(), Ok(palette));
+        assert_eq!("000000,FFFFFF".parse(), Ok(Palette::default()));
+    }
+
+    #[test]
+    fn invalid_palettes_are_rejected() {
+        assert_eq!(
+            "black,white".parse::<Palette>(),
+            Err("invalid colour `black`, expected RRGGBB".to_string())
+        );
+        assert_eq!(
+            "gameboy".parse::<Palette>(),
+            Err("expected `RRGGBB,RRGGBB`, off colour first".to_string())
+        );
+        assert!("000,FFF".parse::<Palette>().is_err());
+        assert!("000000,FFFFFF0".parse::<Palette>().is_err());
+        assert!("00000g,FFFFFF".parse::<Palette>().is_err());
+    }
+
+    #[test]
+    fn indexed_scales_every_pixel() {
+        let style = Style {
+            scale: 3,
+            palette: Palette::default(),
+        };
+        let indexed = style.indexed(&corners());
+        let width = style.width() as usize;
+        assert_eq!(indexed.len(), width * style.height() as usize);
+        assert_eq!(indexed.iter().filter(|&&on| on == 1).count(), 2 * 9);
+        for row in 0..3 {
+            assert_eq!(indexed[row * width..][..4], [1, 1, 1, 0]);
+        }
+        assert_eq!(indexed[3 * width], 0);
+        assert_eq!(indexed[indexed.len() - 4..], [0, 1, 1, 1]);
+    }
+
+    #[test]
+    fn pbm_has_one_digit_per_pixel() {
+        let path = std::env::temp_dir().join(format!("solchip8-{}-frame.pbm", std::process::id()));
+        save(&path, &corners(), &Style::default()).unwrap();
+        let bytes = fs::read(&path).unwrap();
+        let _ = fs::remove_file(&path);
+
+        let header = format!("P1\n{SCREEN_WIDTH} {SCREEN_HEIGHT}\n");
+        let rows = bytes.strip_prefix(header.as_bytes()).unwrap();
+        assert_eq!(rows.len(), SCREEN_HEIGHT * (SCREEN_WIDTH + 1));
+        let rows: Vec<_> = rows.split(|&b| b == b'\n').collect();
+        assert_eq!(
+            rows[0],
+            [b"1".as_slice(), &[b'0'; SCREEN_WIDTH - 1]].concat()
+        );
+        assert!(rows[1..SCREEN_HEIGHT - 1]
+            .iter()
+            .all(|row| *row == [b'0'; SCREEN_WIDTH]));
+        assert_eq!(
+            rows[SCREEN_HEIGHT - 1],
+            [[b'0'; SCREEN_WIDTH - 1].as_slice(), b"1"].concat()
+        );
+    }
+}
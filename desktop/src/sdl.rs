@@ -0,0 +1,451 @@
+//! SDL frontend: the game window with the optional RAM and sprite windows, keyboard input and
+//! the debugger hotkeys.
+//!
+//! code is credit from https://github.com/aquova/chip8-book/tree/master/code/desktop
+
+use std::io;
+
+use bitvec::prelude::*;
+
+use alloy::network::{Network, ReceiptResponse};
+use alloy::primitives::{TxHash, U256};
+use alloy::providers::Provider;
+use alloy::transports::Transport;
+use desktop::client;
+use desktop::display::Screen;
+use desktop::{SCREEN_HEIGHT, SCREEN_WIDTH};
+use sdl2::event::Event;
+use sdl2::keyboard::Keycode;
+use sdl2::pixels::Color;
+use sdl2::rect::Rect;
+use sdl2::render::Canvas;
+use sdl2::video::Window;
+
+use crate::callstack;
+use crate::debugger::{CallStack, Command, Debugger};
+use crate::frontend;
+use crate::idle::{self, Idle};
+use crate::memview::MemoryView;
+use crate::profile::Profiler;
+use crate::replay::{self, Recording};
+use crate::screenshot;
+use crate::spriteview::SpriteView;
+use crate::state::Chip8State;
+use crate::tracer::Tracer;
+use crate::Solchip8::Solchip8Instance;
+use crate::{start, PlayArgs};
+
+const SCALE: u32 = 15;
+const WINDOW_WIDTH: u32 = (SCREEN_WIDTH as u32) * SCALE;
+const WINDOW_HEIGHT: u32 = (SCREEN_HEIGHT as u32) * SCALE;
+
+pub async fn play(args: PlayArgs, rpc_url: &str) -> io::Result<()> {
+    let Some(chip8) = start(
+        rpc_url,
+        &args.rom,
+        args.font.as_ref(),
+        args.deterministic,
+    )
+    .await
+    else {
+        std::process::exit(2);
+    };
+
+    // Setup SDL
+    let sdl_context = sdl2::init().unwrap();
+    let video_subsystem = sdl_context.video().unwrap();
+    let window = video_subsystem
+        .window(&title(None), WINDOW_WIDTH, WINDOW_HEIGHT)
+        .position_centered()
+        .opengl()
+        .build()
+        .unwrap();
+
+    let mut canvas = window.into_canvas().present_vsync().build().unwrap();
+    canvas.clear();
+    canvas.present();
+    let mut event_pump = sdl_context.event_pump().unwrap();
+
+    let mut debugger = args.debug.then(|| Debugger::new(args.font.clone(), args.deterministic));
+    let mut memview = args.memory.then(|| MemoryView::new(&video_subsystem));
+    let mut spriteview = args.sprites.then(|| SpriteView::new(&video_subsystem));
+    // Set once the next tick() would revert, the window stays open without ticking
+    let mut fault = None;
+    let mut tracer = match args.trace.as_deref().map(Tracer::create).transpose() {
+        Ok(tracer) => tracer,
+        Err(e) => {
+            println!("Tracing failed: {}", e);
+            std::process::exit(2);
+        }
+    };
+    let mut profiler = args.profile.then(Profiler::new);
+    let mut recorder = match args.record.recorder(args.image.style()) {
+        Ok(recorder) => recorder,
+        Err(e) => {
+            println!("Recording failed: {}", e);
+            std::process::exit(2);
+        }
+    };
+    let recording = std::time::Instant::now();
+    let mut replay = match &args.save_replay {
+        Some(path) => match Recording::start(
+            &chip8,
+            &args.rom,
+            args.font.as_ref(),
+            path,
+            args.deterministic,
+        )
+        .await
+        {
+            Ok(recording) => Some(recording),
+            Err(e) => {
+                println!("Replay recording failed: {}", e);
+                std::process::exit(2);
+            }
+        },
+        None => None,
+    };
+    // With --deterministic, keys held by the player and as last sent before a tick
+    let (mut held, mut sent) = (0u16, 0u16);
+    // Set while the program cannot move without input, ticks are held back
+    let mut idle = None;
+    // PC after the last tick, and whether that tick left it where it was; only such a tick
+    // can leave the program idle, so only then is it worth asking the contract
+    let mut last_pc = None;
+    let mut stalled = false;
+    let mut screen = Screen::new(&chip8).await;
+
+    'gameloop: loop {
+        let now = std::time::Instant::now();
+        // A key went to the contract this frame, the next tick has to see it
+        let mut input = false;
+        for evt in event_pump.poll_iter() {
+            if let Some(view) = memview.as_mut().filter(|view| view.owns(&evt)) {
+                match view.handle(&evt, &chip8).await {
+                    Ok(true) => {}
+                    Ok(false) => memview = None,
+                    Err(e) => println!("RAM view failed: {e}"),
+                }
+                continue;
+            }
+            if let Some(view) = spriteview.as_mut().filter(|view| view.owns(&evt)) {
+                if !view.handle(&evt, &chip8).await {
+                    spriteview = None;
+                }
+                continue;
+            }
+            match evt {
+                Event::Quit { .. }
+                | Event::KeyDown {
+                    keycode: Some(Keycode::Escape),
+                    ..
+                } => {
+                    break 'gameloop;
+                }
+                Event::KeyDown {
+                    keycode: Some(Keycode::F2),
+                    ..
+                } => {
+                    memview = match memview {
+                        Some(_) => None,
+                        None => Some(MemoryView::new(&video_subsystem)),
+                    };
+                }
+                Event::KeyDown {
+                    keycode: Some(Keycode::F3),
+                    ..
+                } => {
+                    spriteview = match spriteview {
+                        Some(_) => None,
+                        None => Some(SpriteView::new(&video_subsystem)),
+                    };
+                }
+                Event::KeyDown {
+                    keycode: Some(Keycode::F12),
+                    ..
+                } => {
+                    let path = frontend::next_path();
+                    match screenshot::capture(&chip8, &path, &args.image.style()).await {
+                        Ok(()) => println!("📷 saved {}", path.display()),
+                        Err(e) => println!("Screenshot failed: {}", e),
+                    }
+                }
+                Event::KeyDown {
+                    keycode: Some(key), ..
+                } => {
+                    if let Some(debugger) = debugger.as_mut() {
+                        if let Some(cmd) = key2cmd(key, debugger) {
+                            if let Err(e) = debugger.command(cmd, &chip8).await {
+                                println!("{e}");
+                            }
+                            continue;
+                        }
+                    }
+                    if let Some(k) = key2btn(key) {
+                        if args.deterministic {
+                            held |= 1 << k;
+                            continue;
+                        }
+                        let builder = chip8.keypress(U256::from(k), true);
+                        builder.call().await.unwrap();
+
+                        let keys = chip8.getKeys().call().await.unwrap();
+                        println!("keys: {:?}", keys._0);
+
+                        if keys._0  & (1 << k) == 0{
+                            input = true;
+                            let tx = builder.send().await.unwrap();
+                            println!("⭐️ key {:?} down tx: {:?}", key, tx);
+                        }
+                      
+                    }
+                }
+                Event::KeyUp {
+                    keycode: Some(key), ..
+                } => {
+                    if let Some(k) = key2btn(key) {
+                        if args.deterministic {
+                            held &= !(1 << k);
+                            continue;
+                        }
+                        let builder = chip8.keypress(U256::from(k), false);
+                        builder.call().await.unwrap();
+
+                        let keys = chip8.getKeys().call().await.unwrap();
+                        println!("keys: {:?}", keys._0);
+
+                        if keys._0  & (1 << k) != 0{
+                            input = true;
+                            let tx = builder.send().await.unwrap();
+                            println!("⭐️ key {:?} up tx: {:?}", key, tx);
+                        }
+                    }
+                }
+                _ => (),
+            }
+        }
+
+        if let Some(debugger) = debugger.as_mut() {
+            debugger.poll(&chip8).await;
+            if debugger.should_tick() {
+                if let Some(fault) = callstack::check(&chip8).await.map_err(io::Error::other)? {
+                    if let Err(e) = debugger.fault(fault, &chip8).await {
+                        println!("{e}");
+                    }
+                    continue;
+                }
+                if args.deterministic {
+                    replay::send_keys(&chip8, sent, held).await.unwrap();
+                    sent = held;
+                }
+                if tracer.is_some() || profiler.is_some() || replay.is_some() {
+                    recorded_tick(&chip8, tracer.as_mut(), profiler.as_mut(), replay.as_mut())
+                        .await;
+                } else {
+                    let builder = chip8.tick();
+                    builder.call().await.unwrap();
+                    // Wait for the tick to be mined so the panel shows its result
+                    builder.send().await.unwrap().get_receipt().await.unwrap();
+                }
+                if let Err(e) = debugger.after_tick(&chip8).await {
+                    println!("{e}");
+                }
+            }
+        } else if fault.is_none() {
+            fault = callstack::check(&chip8).await.map_err(io::Error::other)?;
+            if let Some(fault) = fault {
+                println!("⛔️ {fault}, stopped before sending tick()");
+                let stack = CallStack::fetch(&chip8).await.map_err(io::Error::other)?;
+                println!("{stack}");
+                continue;
+            }
+            // Keys held but not sent yet are input too, they go out with the next tick
+            let now_idle = if input || (args.deterministic && held != sent) {
+                None
+            } else if std::mem::take(&mut stalled) {
+                idle::check(&chip8).await.map_err(io::Error::other)?
+            } else {
+                idle
+            };
+            if now_idle != idle {
+                match now_idle {
+                    Some(now_idle) => {
+                        println!("💤 {now_idle}, no tick() until input or the timers change")
+                    }
+                    None => println!("▶️ resumed"),
+                }
+                canvas.window_mut().set_title(&title(now_idle)).unwrap();
+                idle = now_idle;
+            }
+            if idle.is_none() {
+                if args.deterministic {
+                    replay::send_keys(&chip8, sent, held).await.unwrap();
+                    sent = held;
+                }
+                if tracer.is_some() || profiler.is_some() || replay.is_some() {
+                    let tx =
+                        recorded_tick(&chip8, tracer.as_mut(), profiler.as_mut(), replay.as_mut())
+                            .await;
+                    println!("tx for tick:{:?}", tx);
+                } else {
+                    let builder = chip8.tick();
+                    builder.call().await.unwrap();
+                    let tx = builder.send().await.unwrap();
+                    println!("tx for tick:{:?}", tx);
+                }
+
+                let pc = chip8.getPC().call().await.unwrap();
+                println!("PC: {:?}", pc._0);
+                stalled = last_pc.replace(pc._0) == Some(pc._0);
+
+                let keys = chip8.getKeys().call().await.unwrap();
+                println!("keys: {:?}", keys._0);
+
+                let dt = chip8.getDelayTimer().call().await.unwrap();
+                println!("DT: {:?}", dt._0);
+            }
+        }
+
+        // Redrawn from the cached words until a new block is mined
+        screen.refresh(&chip8).await.unwrap();
+        draw_screen(screen.words(), &mut canvas);
+        if let Some(recorder) = recorder.as_mut() {
+            recorder.frame(recording.elapsed(), &screen.pixels()).unwrap();
+        }
+        if let Some(view) = memview.as_mut() {
+            if let Err(e) = view.draw(&chip8).await {
+                println!("RAM view failed: {e}");
+            }
+        }
+        if let Some(view) = spriteview.as_mut() {
+            if let Err(e) = view.draw(&chip8).await {
+                println!("Sprite view failed: {e}");
+            }
+        }
+
+        let end = std::time::Instant::now();
+        println!("⌛️ duration :{:?}", end.duration_since(now));
+    }
+
+    if let Some(profiler) = profiler {
+        println!("{profiler}");
+    }
+    if let Some(recorder) = recorder {
+        let frames = recorder.finish(recording.elapsed()).unwrap();
+        println!("🎬 recorded {frames} frames");
+    }
+    if let Some(replay) = replay {
+        let ticks = replay.finish(&chip8).await.unwrap();
+        println!("💾 saved replay of {ticks} ticks");
+    }
+    Ok(())
+}
+
+/// Window title, saying why ticks are held back.
+fn title(idle: Option<Idle>) -> String {
+    match idle {
+        None => "Chip-8 Emulator".to_string(),
+        Some(Idle::Halted { .. }) => "Chip-8 Emulator (halted)".to_string(),
+        Some(Idle::WaitingForKey { .. }) => "Chip-8 Emulator (waiting for key)".to_string(),
+    }
+}
+
+/// Send a tick() and wait for it to be mined, feeding the trace, the profiler and the replay.
+async fn recorded_tick<T, P, N>(
+    emu: &Solchip8Instance<T, P, N>,
+    tracer: Option<&mut Tracer>,
+    profiler: Option<&mut Profiler>,
+    replay: Option<&mut Recording>,
+) -> TxHash
+where
+    T: Transport + Clone,
+    P: Provider<T, N>,
+    N: Network,
+{
+    let state = Chip8State::fetch(emu).await.unwrap();
+    let receipt = client::transact(emu.tick()).await.unwrap();
+    let (tx, gas) = (receipt.transaction_hash(), receipt.gas_used() as u64);
+    if let Some(tracer) = tracer {
+        tracer.write(&state, tx, gas).unwrap();
+    }
+    if let Some(profiler) = profiler {
+        profiler.record(emu, state.opcode, tx, gas).await.unwrap();
+    }
+    if let Some(replay) = replay {
+        replay.tick(emu).await.unwrap();
+    }
+    tx
+}
+
+fn draw_screen(screen_buf: &[U256], canvas: &mut Canvas<Window>) {
+    // Clear canvas as black
+    canvas.set_draw_color(Color::RGB(0, 0, 0));
+    canvas.clear();
+
+    // Now set draw color to white, iterate through each point and see if it should be drawn
+    canvas.set_draw_color(Color::RGB(255, 255, 255));
+    for (i, pixel) in screen_buf.iter().enumerate() {
+        let pixel_bytes:[u8; 32]  = pixel.to_be_bytes();
+        for (j, pixel_byte) in pixel_bytes.into_iter().enumerate() {
+            let pixel_bits = pixel_byte.view_bits::<Msb0>();
+            for (k, pixel_bit) in pixel_bits.into_iter().enumerate() {
+                if *pixel_bit {
+                    let pixel_index= k + (j * 8) + (i * 256);
+                    // Convert our 1D array's pixel_index into a 2D (x,y) position
+                    let x = (pixel_index % SCREEN_WIDTH) as u32;
+                    let y = (pixel_index / SCREEN_WIDTH) as u32;
+        
+                    // Draw a rectangle at (x,y), scaled up by our SCALE value
+                    let rect = Rect::new((x * SCALE) as i32, (y * SCALE) as i32, SCALE, SCALE);
+                    canvas.fill_rect(rect).unwrap();
+                }
+            }
+        }
+       
+    }
+    canvas.present();
+}
+
+/*
+    Keyboard                    Chip-8
+    +---+---+---+---+           +---+---+---+---+
+    | 1 | 2 | 3 | 4 |           | 1 | 2 | 3 | C |
+    +---+---+---+---+           +---+---+---+---+
+    | Q | W | E | R |           | 4 | 5 | 6 | D |
+    +---+---+---+---+     =>    +---+---+---+---+
+    | A | S | D | F |           | 7 | 8 | 9 | E |
+    +---+---+---+---+           +---+---+---+---+
+    | Z | X | C | V |           | A | 0 | B | F |
+    +---+---+---+---+           +---+---+---+---+
+*/
+
+fn key2cmd(key: Keycode, debugger: &Debugger) -> Option<Command> {
+    match key {
+        Keycode::F5 => Some(debugger.toggle_pause()),
+        Keycode::F10 => Some(Command::StepOver),
+        Keycode::F11 => Some(Command::Step),
+        _ => None,
+    }
+}
+
+fn key2btn(key: Keycode) -> Option<usize> {
+    match key {
+        Keycode::Num1 => Some(0x1),
+        Keycode::Num2 => Some(0x2),
+        Keycode::Num3 => Some(0x3),
+        Keycode::Num4 => Some(0xC),
+        Keycode::Q => Some(0x4),
+        Keycode::W => Some(0x5),
+        Keycode::E => Some(0x6),
+        Keycode::R => Some(0xD),
+        Keycode::A => Some(0x7),
+        Keycode::S => Some(0x8),
+        Keycode::D => Some(0x9),
+        Keycode::F => Some(0xE),
+        Keycode::Z => Some(0xA),
+        Keycode::X => Some(0x0),
+        Keycode::C => Some(0xB),
+        Keycode::V => Some(0xF),
+        _ => None,
+    }
+}
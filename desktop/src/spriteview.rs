@@ -0,0 +1,342 @@
+//! Sprite inspector in a second SDL window.
+//!
+//! The top row shows the 16 font glyphs as they currently are in RAM 0x000-0x04F. Below, the
+//! bytes at I are drawn as an 8xN sprite next to their hex values, with the I address above.
+//! When the instruction at PC is a `DXYN`, the sprite is cut to N rows and the screen preview
+//! on the right shows where it lands: the X and Y coordinates above it, pixels it would turn
+//! on in green and pixels it would erase (setting VF) in red. Rows that would be read past
+//! 0xFFF, which makes `tick()` revert, are shown as red bars.
+//!
+//! Left/Right preview the built-in fonts in the glyph row without touching the contract, and
+//! Enter writes the previewed font into RAM.
+
+use alloy::network::Network;
+use alloy::primitives::U256;
+use alloy::providers::Provider;
+use alloy::transports::Transport;
+use desktop::display;
+use desktop::{SCREEN_HEIGHT, SCREEN_WIDTH};
+use sdl2::event::{Event, WindowEvent};
+use sdl2::keyboard::Keycode;
+use sdl2::pixels::Color;
+use sdl2::rect::Rect;
+use sdl2::render::Canvas;
+use sdl2::video::Window;
+use sdl2::VideoSubsystem;
+
+use crate::disasm::disassemble;
+use crate::font::{Font, BUILTIN};
+use crate::memview::{fill, glyph, sprite, DIGIT_WIDTH};
+use crate::rom::{FONTSET_SIZE, RAM_SIZE};
+use crate::state::read_opcode;
+use crate::storage;
+use crate::Solchip8::Solchip8Instance;
+
+/// Longest sprite `DXYN` can draw.
+const MAX_ROWS: usize = 15;
+
+const MARGIN: i32 = 6;
+const FONT_DOT: u32 = 4;
+const FONT_GAP: i32 = 6;
+const SPRITE_DOT: u32 = 8;
+const PREVIEW_DOT: u32 = 4;
+/// Top of the I address and coordinate labels.
+const LABEL_Y: i32 = MARGIN + 5 * FONT_DOT as i32 + 12;
+/// Top of the sprite and the screen preview.
+const BODY_Y: i32 = LABEL_Y + 5 * 2 + 6;
+const HEX_X: i32 = MARGIN + 8 * SPRITE_DOT as i32 + 8;
+const PREVIEW_X: i32 = HEX_X + 2 * DIGIT_WIDTH as i32 + 16;
+const WINDOW_WIDTH: u32 = 2 * MARGIN as u32 + 16 * (8 * FONT_DOT + FONT_GAP as u32);
+/// The screen preview is taller than the longest sprite.
+const WINDOW_HEIGHT: u32 = BODY_Y as u32 + SCREEN_HEIGHT as u32 * PREVIEW_DOT + 2 + MARGIN as u32;
+
+const BACKGROUND: Color = Color::RGB(0, 0, 0);
+const FONT: Color = Color::RGB(120, 140, 255);
+const FONT_PREVIEW: Color = Color::RGB(255, 160, 0);
+const SPRITE: Color = Color::RGB(255, 255, 255);
+const TEXT: Color = Color::RGB(200, 200, 200);
+const LABEL: Color = Color::RGB(230, 0, 230);
+const SCREEN: Color = Color::RGB(70, 70, 70);
+const SCREEN_BORDER: Color = Color::RGB(110, 110, 110);
+const DRAW_ON: Color = Color::RGB(0, 220, 0);
+const DRAW_OFF: Color = Color::RGB(230, 40, 40);
+const PAST_RAM: Color = Color::RGB(230, 40, 40);
+
+/// What the `DXYN` at PC would draw.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct Draw {
+    pc: u16,
+    op: u16,
+    x: u8,
+    y: u8,
+    rows: usize,
+}
+
+pub struct SpriteView {
+    canvas: Canvas<Window>,
+    /// Last preview printed, so each upcoming draw is logged once.
+    last: Option<Draw>,
+    /// Built-in font shown instead of RAM 0x000-0x04F.
+    font: Option<Font>,
+}
+
+impl SpriteView {
+    pub fn new(video: &VideoSubsystem) -> Self {
+        let window = video
+            .window("Chip-8 sprites", WINDOW_WIDTH, WINDOW_HEIGHT)
+            .opengl()
+            .build()
+            .unwrap();
+        let canvas = window.into_canvas().build().unwrap();
+        SpriteView {
+            canvas,
+            last: None,
+            font: None,
+        }
+    }
+
+    /// Whether `event` was sent to this window.
+    pub fn owns(&self, event: &Event) -> bool {
+        let id = self.canvas.window().id();
+        match *event {
+            Event::Window { window_id, .. }
+            | Event::KeyDown { window_id, .. }
+            | Event::KeyUp { window_id, .. } => window_id == id,
+            _ => false,
+        }
+    }
+
+    /// Handle an event for this window. Returns false once the window is closed.
+    pub async fn handle<T, P, N>(&mut self, event: &Event, emu: &Solchip8Instance<T, P, N>) -> bool
+    where
+        T: Transport + Clone,
+        P: Provider<T, N>,
+        N: Network,
+    {
+        match *event {
+            Event::Window {
+                win_event: WindowEvent::Close,
+                ..
+            }
+            | Event::KeyDown {
+                keycode: Some(Keycode::Escape),
+                ..
+            } => return false,
+            Event::KeyDown {
+                keycode: Some(Keycode::Left),
+                ..
+            } => self.cycle_font(BUILTIN.len()),
+            Event::KeyDown {
+                keycode: Some(Keycode::Right),
+                ..
+            } => self.cycle_font(1),
+            Event::KeyDown {
+                keycode: Some(Keycode::Return),
+                ..
+            } => {
+                if let Some(font) = self.font.take() {
+                    if let Err(e) = font.install(emu).await {
+                        println!("font install failed: {e}");
+                    }
+                }
+            }
+            _ => (),
+        }
+        true
+    }
+
+    /// Move the font preview `step` places along RAM, then each built-in font.
+    fn cycle_font(&mut self, step: usize) {
+        let current = match &self.font {
+            Some(font) => {
+                BUILTIN
+                    .iter()
+                    .position(|(name, _)| *name == font.name)
+                    .unwrap()
+                    + 1
+            }
+            None => 0,
+        };
+        let next = (current + step) % (BUILTIN.len() + 1);
+        self.font = next.checked_sub(1).map(|idx| {
+            let font = Font::builtin(BUILTIN[idx].0).unwrap();
+            println!("previewing font {}, Enter writes it to RAM", font.name);
+            font
+        });
+    }
+
+    pub async fn draw<T, P, N>(
+        &mut self,
+        emu: &Solchip8Instance<T, P, N>,
+    ) -> Result<(), alloy::contract::Error>
+    where
+        T: Transport + Clone,
+        P: Provider<T, N>,
+        N: Network,
+    {
+        let (font, font_color) = match &self.font {
+            Some(font) => (font.bytes.to_vec(), FONT_PREVIEW),
+            None => (storage::read_ram(emu, 0, FONTSET_SIZE).await?, FONT),
+        };
+        let i = emu.getIRegister().call().await?._0;
+        let pc = emu.getPC().call().await?._0;
+        let op = read_opcode(emu, pc).await?;
+
+        let draw = if op & 0xF000 == 0xD000 {
+            let vx = U256::from((op >> 8) & 0xF);
+            let vy = U256::from((op >> 4) & 0xF);
+            Some(Draw {
+                pc,
+                op,
+                x: emu.getVRegister(vx).call().await?._0 % SCREEN_WIDTH as u8,
+                y: emu.getVRegister(vy).call().await?._0 % SCREEN_HEIGHT as u8,
+                rows: (op & 0xF) as usize,
+            })
+        } else {
+            None
+        };
+        let rows = draw.map_or(MAX_ROWS, |draw| draw.rows);
+        // `read_ram` stops at the end of RAM, the rows after it do not exist
+        let bytes = storage::read_ram(emu, i as usize, rows).await?;
+        let past_ram = rows - bytes.len();
+
+        if draw != self.last {
+            if let Some(draw) = draw {
+                println!(
+                    "next draw at {:#05x}: {} -> ({}, {}), {} rows from I {:#05x}",
+                    draw.pc,
+                    disassemble(draw.op),
+                    draw.x,
+                    draw.y,
+                    draw.rows,
+                    i
+                );
+                if past_ram > 0 {
+                    println!(
+                        "⚠️ {past_ram} rows lie past {:#05x}, the tick() would revert",
+                        RAM_SIZE - 1
+                    );
+                }
+            }
+            self.last = draw;
+        }
+
+        self.canvas.set_draw_color(BACKGROUND);
+        self.canvas.clear();
+
+        let mut rects = Vec::new();
+        for (n, glyph_rows) in font.chunks(5).enumerate() {
+            let x = MARGIN + n as i32 * (8 * FONT_DOT as i32 + FONT_GAP);
+            sprite(&mut rects, x, MARGIN, glyph_rows, 8, FONT_DOT);
+        }
+        fill(&mut self.canvas, font_color, &rects);
+
+        let mut rects = Vec::new();
+        hex(&mut rects, MARGIN, LABEL_Y, i as u32, 3);
+        fill(&mut self.canvas, LABEL, &rects);
+
+        let mut rects = Vec::new();
+        sprite(&mut rects, MARGIN, BODY_Y, &bytes, 8, SPRITE_DOT);
+        fill(&mut self.canvas, SPRITE, &rects);
+
+        let mut rects = Vec::new();
+        for (row, &byte) in bytes.iter().enumerate() {
+            let y = BODY_Y + (row as u32 * SPRITE_DOT) as i32 - 1;
+            hex(&mut rects, HEX_X, y, byte as u32, 2);
+        }
+        fill(&mut self.canvas, TEXT, &rects);
+
+        let rects: Vec<_> = (bytes.len()..rows)
+            .map(|row| {
+                let y = BODY_Y + (row as u32 * SPRITE_DOT) as i32;
+                Rect::new(MARGIN, y, 8 * SPRITE_DOT, SPRITE_DOT - 2)
+            })
+            .collect();
+        fill(&mut self.canvas, PAST_RAM, &rects);
+
+        if let Some(draw) = draw {
+            self.preview(emu, draw, &bytes).await?;
+        }
+        self.canvas.present();
+        Ok(())
+    }
+
+    /// Draw the current screen with the pending sprite XORed on top.
+    async fn preview<T, P, N>(
+        &mut self,
+        emu: &Solchip8Instance<T, P, N>,
+        draw: Draw,
+        bytes: &[u8],
+    ) -> Result<(), alloy::contract::Error>
+    where
+        T: Transport + Clone,
+        P: Provider<T, N>,
+        N: Network,
+    {
+        let screen = display::decode(&emu.getDisplay().call().await?._0);
+
+        let mut rects = Vec::new();
+        hex(&mut rects, PREVIEW_X, LABEL_Y, draw.x as u32, 2);
+        hex(
+            &mut rects,
+            PREVIEW_X + 3 * DIGIT_WIDTH as i32,
+            LABEL_Y,
+            draw.y as u32,
+            2,
+        );
+        fill(&mut self.canvas, LABEL, &rects);
+
+        let border = Rect::new(
+            PREVIEW_X - 1,
+            BODY_Y - 1,
+            SCREEN_WIDTH as u32 * PREVIEW_DOT + 2,
+            SCREEN_HEIGHT as u32 * PREVIEW_DOT + 2,
+        );
+        self.canvas.set_draw_color(SCREEN_BORDER);
+        self.canvas.draw_rect(border).unwrap();
+
+        let mut sprite_pixels = [false; display::PIXELS];
+        for (row, &byte) in bytes.iter().enumerate() {
+            for col in 0..8 {
+                if byte & (0x80 >> col) != 0 {
+                    let x = (draw.x as usize + col) % SCREEN_WIDTH;
+                    let y = (draw.y as usize + row) % SCREEN_HEIGHT;
+                    sprite_pixels[y * SCREEN_WIDTH + x] = true;
+                }
+            }
+        }
+
+        let (mut lit, mut on, mut off) = (Vec::new(), Vec::new(), Vec::new());
+        for (idx, (&pixel, &drawn)) in screen.iter().zip(&sprite_pixels).enumerate() {
+            let rect = Rect::new(
+                PREVIEW_X + ((idx % SCREEN_WIDTH) as u32 * PREVIEW_DOT) as i32,
+                BODY_Y + ((idx / SCREEN_WIDTH) as u32 * PREVIEW_DOT) as i32,
+                PREVIEW_DOT,
+                PREVIEW_DOT,
+            );
+            match (pixel, drawn) {
+                (true, true) => off.push(rect),
+                (false, true) => on.push(rect),
+                (true, false) => lit.push(rect),
+                (false, false) => {}
+            }
+        }
+        fill(&mut self.canvas, SCREEN, &lit);
+        fill(&mut self.canvas, DRAW_ON, &on);
+        fill(&mut self.canvas, DRAW_OFF, &off);
+        Ok(())
+    }
+}
+
+/// Queue `digits` hex digits of `value`, most significant first.
+fn hex(rects: &mut Vec<Rect>, x: i32, y: i32, value: u32, digits: u32) {
+    for n in 0..digits {
+        let shift = 4 * (digits - 1 - n);
+        glyph(
+            rects,
+            x + (n * DIGIT_WIDTH) as i32,
+            y,
+            (value >> shift) as u8 & 0xF,
+        );
+    }
+}
@@ -0,0 +1,111 @@
+//! Snapshot of the emulator registers, read back from the contract.
+
+use std::fmt;
+
+use alloy::network::Network;
+use alloy::primitives::U256;
+use alloy::providers::Provider;
+use alloy::transports::Transport;
+
+use crate::disasm::disassemble;
+use crate::rom::RAM_SIZE;
+use crate::Solchip8::Solchip8Instance;
+
+/// Number of general-purpose registers.
+pub const NUM_REGS: usize = 16;
+/// Number of stack entries.
+pub const STACK_SIZE: usize = 16;
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Chip8State {
+    pub pc: u16,
+    /// Opcode at `pc`, i.e. the instruction the next `tick()` executes.
+    pub opcode: u16,
+    pub v: [u8; NUM_REGS],
+    pub i: u16,
+    pub sp: u16,
+    pub stack: [u16; STACK_SIZE],
+    pub dt: u8,
+    pub st: u8,
+    pub keys: u16,
+}
+
+impl Chip8State {
+    pub async fn fetch<T, P, N>(
+        emu: &Solchip8Instance<T, P, N>,
+    ) -> Result<Self, alloy::contract::Error>
+    where
+        T: Transport + Clone,
+        P: Provider<T, N>,
+        N: Network,
+    {
+        let mut state = Chip8State {
+            pc: emu.getPC().call().await?._0,
+            i: emu.getIRegister().call().await?._0,
+            sp: emu.getSP().call().await?._0,
+            dt: emu.getDelayTimer().call().await?._0,
+            st: emu.getSoundTimer().call().await?._0,
+            keys: emu.getKeys().call().await?._0,
+            ..Default::default()
+        };
+        for (idx, v) in state.v.iter_mut().enumerate() {
+            *v = emu.getVRegister(U256::from(idx)).call().await?._0;
+        }
+        for (idx, value) in state.stack.iter_mut().enumerate() {
+            *value = emu.getStackValue(U256::from(idx)).call().await?._0;
+        }
+        state.opcode = read_opcode(emu, state.pc).await?;
+        Ok(state)
+    }
+}
+
+/// Opcode stored at `addr`, or 0 when it would run past the end of RAM.
+pub async fn read_opcode<T, P, N>(
+    emu: &Solchip8Instance<T, P, N>,
+    addr: u16,
+) -> Result<u16, alloy::contract::Error>
+where
+    T: Transport + Clone,
+    P: Provider<T, N>,
+    N: Network,
+{
+    if addr as usize + 1 >= RAM_SIZE {
+        return Ok(0);
+    }
+    let hi = emu.getRAMValueAt(U256::from(addr)).call().await?._0;
+    let lo = emu.getRAMValueAt(U256::from(addr + 1)).call().await?._0;
+    Ok(u16::from_be_bytes([hi, lo]))
+}
+
+impl fmt::Display for Chip8State {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(
+            f,
+            "PC {:#05x}  {:04X}  {}",
+            self.pc,
+            self.opcode,
+            disassemble(self.opcode)
+        )?;
+        for (half, regs) in self.v.chunks(8).enumerate() {
+            for (idx, v) in regs.iter().enumerate() {
+                write!(f, "V{:X} {:02X}  ", half * 8 + idx, v)?;
+            }
+            writeln!(f)?;
+        }
+        writeln!(
+            f,
+            "I  {:#05x}  SP {:2}  DT {:02X}  ST {:02X}",
+            self.i, self.sp, self.dt, self.st
+        )?;
+        write!(f, "stack:")?;
+        for value in self.stack.iter().take((self.sp as usize).min(STACK_SIZE)) {
+            write!(f, " {value:#05x}")?;
+        }
+        writeln!(f)?;
+        write!(f, "keys: {:016b}", self.keys)?;
+        for key in (0..16).filter(|k| self.keys & (1 << k) != 0) {
+            write!(f, " {key:X}")?;
+        }
+        Ok(())
+    }
+}
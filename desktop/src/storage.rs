@@ -0,0 +1,58 @@
+//! Direct reads of the contract's storage, for bulk access where one getter call per byte or
+//! register would be too slow.
+//!
+//! `Solchip8` storage layout, as assigned by solc:
+//!
+//! ```text
+//! slot 0-2      FONTSET          uint8[80]
+//! slot 3        emu.pc           uint16
+//! slot 4-131    emu.ram          uint8[4096], 32 bytes per slot
+//! slot 132-139  emu.screen       uint256[8]
+//! slot 140      emu.v_reg        uint8[16]
+//! slot 141      emu.i_reg        uint16, emu.sp uint16
+//! slot 142      emu.stack        uint16[16]
+//! slot 143      emu.keys         uint16, emu.dt uint8, emu.st uint8
+//! slot 144      emu.program_size uint256
+//! ```
+//!
+//! Packed values start at the lowest-order byte of their slot, so RAM address `a` is byte
+//! `a % 32` counted from the right of slot `4 + a / 32`.
+
+use alloy::network::Network;
+use alloy::primitives::U256;
+use alloy::providers::Provider;
+use alloy::transports::{Transport, TransportResult};
+
+use crate::rom::RAM_SIZE;
+use crate::Solchip8::Solchip8Instance;
+
+/// First storage slot of `emu.ram`.
+pub const RAM_SLOT: usize = 4;
+
+/// Read `len` bytes of RAM starting at `start`, one `eth_getStorageAt` per 32 bytes.
+pub async fn read_ram<T, P, N>(
+    emu: &Solchip8Instance<T, P, N>,
+    start: usize,
+    len: usize,
+) -> TransportResult<Vec<u8>>
+where
+    T: Transport + Clone,
+    P: Provider<T, N>,
+    N: Network,
+{
+    let end = (start + len).min(RAM_SIZE);
+    let mut bytes = Vec::with_capacity(end.saturating_sub(start));
+    let mut addr = start;
+    while addr < end {
+        let slot = U256::from(RAM_SLOT + addr / 32);
+        let word: [u8; 32] = emu
+            .provider()
+            .get_storage_at(*emu.address(), slot)
+            .await?
+            .to_le_bytes();
+        let slot_end = ((addr / 32 + 1) * 32).min(end);
+        bytes.extend_from_slice(&word[addr % 32..addr % 32 + (slot_end - addr)]);
+        addr = slot_end;
+    }
+    Ok(bytes)
+}
@@ -0,0 +1,344 @@
+//! Instruction trace: one record per `tick()`, written as JSONL or a compact binary format by
+//! `tracer`, and a diff of two traces that finds the first cycle where they diverge.
+//!
+//! Each record holds the state the instruction started from (PC, the opcode there,
+//! registers, I, SP, timers and keys) together with the hash and gas of the `tick()`
+//! transaction that executed it. Files ending in `.bin` use the binary format: the magic
+//! `C8TR`, a version byte, then fixed size little-endian records of `RECORD_SIZE` bytes.
+//! Anything else is JSONL.
+
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::Path;
+
+use alloy::primitives::TxHash;
+use serde_json::Value;
+
+use crate::disasm::disassemble;
+use crate::state::NUM_REGS;
+
+pub const MAGIC: &[u8; 4] = b"C8TR";
+pub const VERSION: u8 = 1;
+/// cycle 8, pc 2, opcode 2, v 16, i 2, sp 2, dt 1, st 1, keys 2, tx 32, gas 8
+pub const RECORD_SIZE: usize = 76;
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Record {
+    pub cycle: u64,
+    pub pc: u16,
+    pub opcode: u16,
+    pub v: [u8; NUM_REGS],
+    pub i: u16,
+    pub sp: u16,
+    pub dt: u8,
+    pub st: u8,
+    pub keys: u16,
+    pub tx: TxHash,
+    pub gas: u64,
+}
+
+impl Record {
+    fn from_json(line: &str) -> Result<Self, String> {
+        let value: Value = serde_json::from_str(line).map_err(|e| e.to_string())?;
+        let int = |key: &str| {
+            value[key]
+                .as_u64()
+                .ok_or_else(|| format!("missing or invalid `{key}`"))
+        };
+        let mut v = [0; NUM_REGS];
+        let regs = value["v"].as_array().ok_or("missing `v`")?;
+        if regs.len() != NUM_REGS {
+            return Err(format!("`v` needs {NUM_REGS} registers"));
+        }
+        for (reg, value) in v.iter_mut().zip(regs) {
+            *reg = value.as_u64().ok_or("invalid `v`")? as u8;
+        }
+        let tx = value["tx"]
+            .as_str()
+            .and_then(|tx| tx.parse().ok())
+            .ok_or("missing or invalid `tx`")?;
+        Ok(Record {
+            cycle: int("cycle")?,
+            pc: int("pc")? as u16,
+            opcode: int("opcode")? as u16,
+            v,
+            i: int("i")? as u16,
+            sp: int("sp")? as u16,
+            dt: int("dt")? as u8,
+            st: int("st")? as u8,
+            keys: int("keys")? as u16,
+            tx,
+            gas: int("gas")?,
+        })
+    }
+
+    fn from_bytes(bytes: &[u8; RECORD_SIZE]) -> Self {
+        let u16_at = |at: usize| u16::from_le_bytes([bytes[at], bytes[at + 1]]);
+        let u64_at = |at: usize| u64::from_le_bytes(bytes[at..at + 8].try_into().unwrap());
+        Record {
+            cycle: u64_at(0),
+            pc: u16_at(8),
+            opcode: u16_at(10),
+            v: bytes[12..28].try_into().unwrap(),
+            i: u16_at(28),
+            sp: u16_at(30),
+            dt: bytes[32],
+            st: bytes[33],
+            keys: u16_at(34),
+            tx: TxHash::from_slice(&bytes[36..68]),
+            gas: u64_at(68),
+        }
+    }
+
+    /// Names of the execution state fields that differ, leaving out the tx hash and gas.
+    fn differences(&self, other: &Record) -> Vec<&'static str> {
+        let fields = [
+            ("pc", self.pc != other.pc),
+            ("opcode", self.opcode != other.opcode),
+            ("v", self.v != other.v),
+            ("i", self.i != other.i),
+            ("sp", self.sp != other.sp),
+            ("dt", self.dt != other.dt),
+            ("st", self.st != other.st),
+            ("keys", self.keys != other.keys),
+        ];
+        fields
+            .into_iter()
+            .filter(|(_, differs)| *differs)
+            .map(|(name, _)| name)
+            .collect()
+    }
+}
+
+impl fmt::Display for Record {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "#{:<6} {:#05x}  {:04X}  {:<18}",
+            self.cycle,
+            self.pc,
+            self.opcode,
+            disassemble(self.opcode)
+        )?;
+        for v in self.v {
+            write!(f, "{v:02X} ")?;
+        }
+        write!(
+            f,
+            "I {:#05x} SP {} DT {} ST {} keys {:04X} gas {}",
+            self.i, self.sp, self.dt, self.st, self.keys, self.gas
+        )
+    }
+}
+
+/// Read a trace written in either format.
+pub fn read(path: &Path) -> io::Result<Vec<Record>> {
+    let bytes = fs::read(path)?;
+    let invalid = |msg: String| io::Error::new(io::ErrorKind::InvalidData, msg);
+    if let Some(records) = bytes.strip_prefix(MAGIC) {
+        let (&version, records) = records
+            .split_first()
+            .ok_or_else(|| invalid("truncated header".into()))?;
+        if version != VERSION {
+            return Err(invalid(format!("unsupported trace version {version}")));
+        }
+        if records.len() % RECORD_SIZE != 0 {
+            return Err(invalid("truncated record".into()));
+        }
+        return Ok(records
+            .chunks_exact(RECORD_SIZE)
+            .map(|record| Record::from_bytes(record.try_into().unwrap()))
+            .collect());
+    }
+    let text = String::from_utf8(bytes).map_err(|e| invalid(e.to_string()))?;
+    text.lines()
+        .enumerate()
+        .filter(|(_, line)| !line.trim().is_empty())
+        .map(|(n, line)| {
+            Record::from_json(line).map_err(|e| invalid(format!("line {}: {e}", n + 1)))
+        })
+        .collect()
+}
+
+/// Print where two traces diverge. Returns whether they match.
+pub fn diff(a: &Path, b: &Path) -> io::Result<bool> {
+    let report = compare(a, &read(a)?, b, &read(b)?);
+    let matched = report.is_ok();
+    println!("{}", report.unwrap_or_else(|e| e));
+    Ok(matched)
+}
+
+/// Where `left` and `right` diverge as `Err`, or how far they match as `Ok`.
+fn compare(a: &Path, left: &[Record], b: &Path, right: &[Record]) -> Result<String, String> {
+    for (n, (l, r)) in left.iter().zip(right).enumerate() {
+        let fields = l.differences(r);
+        if fields.is_empty() {
+            continue;
+        }
+        let mut report = format!(
+            "traces diverge at cycle {} ({})\n",
+            l.cycle,
+            fields.join(", ")
+        );
+        if let Some(prev) = n.checked_sub(1).map(|n| &left[n]) {
+            report += &format!("  last match {prev}\n");
+        }
+        report += &format!("  {}  {l}\n", a.display());
+        report += &format!("  {}  {r}", b.display());
+        return Err(report);
+    }
+    if left.len() != right.len() {
+        let (short, long) = if left.len() < right.len() {
+            (a, b)
+        } else {
+            (b, a)
+        };
+        return Err(format!(
+            "traces match for {} cycles, then {} ends while {} continues",
+            left.len().min(right.len()),
+            short.display(),
+            long.display()
+        ));
+    }
+    Ok(format!("traces match for all {} cycles", left.len()))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+
+    use super::*;
+    use crate::state::Chip8State;
+    use crate::tracer::Tracer;
+
+    fn state(pc: u16, v0: u8) -> Chip8State {
+        let mut state = Chip8State {
+            pc,
+            opcode: 0x7001,
+            i: 0x300,
+            sp: 2,
+            dt: 60,
+            st: 1,
+            keys: 0x8001,
+            ..Default::default()
+        };
+        state.v[0] = v0;
+        state.v[15] = 0xFF;
+        state
+    }
+
+    /// A trace file in the temp dir, removed when dropped.
+    struct TempTrace(PathBuf);
+
+    impl TempTrace {
+        fn new(name: &str) -> Self {
+            let path = std::env::temp_dir().join(format!("solchip8-{}-{name}", std::process::id()));
+            TempTrace(path)
+        }
+
+        /// Trace `states`, returning the records as written.
+        fn write(&self, states: &[Chip8State]) -> Vec<Record> {
+            let mut tracer = Tracer::create(&self.0).unwrap();
+            states
+                .iter()
+                .enumerate()
+                .map(|(n, state)| {
+                    let tx = TxHash::repeat_byte(n as u8 + 1);
+                    tracer.write(state, tx, 40_000 + n as u64).unwrap()
+                })
+                .collect()
+        }
+    }
+
+    impl Drop for TempTrace {
+        fn drop(&mut self) {
+            let _ = fs::remove_file(&self.0);
+        }
+    }
+
+    #[test]
+    fn records_round_trip_through_both_formats() {
+        let states = [state(0x200, 0), state(0x202, 1), state(0x204, 0xFE)];
+        let jsonl = TempTrace::new("round-trip.jsonl");
+        let binary = TempTrace::new("round-trip.bin");
+        let written = jsonl.write(&states);
+        assert_eq!(binary.write(&states), written);
+
+        assert_eq!(written[2].cycle, 2);
+        assert_eq!(written[2].v[0], 0xFE);
+        assert_eq!(read(&jsonl.0).unwrap(), written);
+        assert_eq!(read(&binary.0).unwrap(), written);
+
+        let bytes = fs::read(&binary.0).unwrap();
+        assert_eq!(&bytes[..4], MAGIC);
+        assert_eq!(bytes[4], VERSION);
+        assert_eq!(bytes.len(), 5 + 3 * RECORD_SIZE);
+    }
+
+    #[test]
+    fn damaged_binary_traces_are_rejected() {
+        let trace = TempTrace::new("damaged.bin");
+        trace.write(&[state(0x200, 0)]);
+        let mut bytes = fs::read(&trace.0).unwrap();
+
+        bytes.pop();
+        fs::write(&trace.0, &bytes).unwrap();
+        assert_eq!(read(&trace.0).unwrap_err().to_string(), "truncated record");
+
+        bytes[4] = VERSION + 1;
+        fs::write(&trace.0, &bytes[..5]).unwrap();
+        assert_eq!(
+            read(&trace.0).unwrap_err().to_string(),
+            format!("unsupported trace version {}", VERSION + 1)
+        );
+    }
+
+    #[test]
+    fn invalid_jsonl_names_the_line() {
+        let trace = TempTrace::new("invalid.jsonl");
+        trace.write(&[state(0x200, 0)]);
+        let mut text = fs::read_to_string(&trace.0).unwrap();
+        text += "\n{\"cycle\": 1}\n";
+        fs::write(&trace.0, text).unwrap();
+        let error = read(&trace.0).unwrap_err().to_string();
+        assert!(error.starts_with("line 3: missing"), "{error}");
+    }
+
+    #[test]
+    fn diff_reports_the_first_divergence() {
+        let a = TempTrace::new("a.jsonl");
+        let b = TempTrace::new("b.bin");
+        let left = a.write(&[state(0x200, 0), state(0x202, 1), state(0x204, 2)]);
+        // Same execution, different tx hashes and gas, then V0 and PC differ
+        let right = b.write(&[state(0x200, 0), state(0x202, 1), state(0x206, 3)]);
+        let report = compare(&a.0, &left, &b.0, &right).unwrap_err();
+        let lines: Vec<_> = report.lines().collect();
+        assert_eq!(lines[0], "traces diverge at cycle 2 (pc, v)");
+        assert_eq!(lines[1], format!("  last match {}", left[1]));
+        assert_eq!(lines[2], format!("  {}  {}", a.0.display(), left[2]));
+        assert_eq!(lines[3], format!("  {}  {}", b.0.display(), right[2]));
+        assert!(!diff(&a.0, &b.0).unwrap());
+    }
+
+    #[test]
+    fn diff_reports_traces_of_different_length() {
+        let a = TempTrace::new("short.jsonl");
+        let b = TempTrace::new("long.jsonl");
+        let short = a.write(&[state(0x200, 0)]);
+        let long = b.write(&[state(0x200, 0), state(0x202, 1)]);
+        assert_eq!(
+            compare(&b.0, &long, &a.0, &short),
+            Err(format!(
+                "traces match for 1 cycles, then {} ends while {} continues",
+                a.0.display(),
+                b.0.display()
+            ))
+        );
+        assert_eq!(
+            compare(&a.0, &short, &a.0, &short),
+            Ok("traces match for all 1 cycles".to_string())
+        );
+        assert!(diff(&a.0, &a.0).unwrap());
+    }
+}
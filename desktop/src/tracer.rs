@@ -0,0 +1,120 @@
+//! Writing side of `trace`: one record per `tick()` as it goes through, in the format the
+//! file name picks.
+
+use std::fs::File;
+use std::io::{self, BufWriter, Write};
+use std::path::Path;
+
+use alloy::primitives::TxHash;
+use serde_json::{json, Value};
+
+use crate::disasm::disassemble;
+use crate::state::Chip8State;
+use crate::trace::{Record, MAGIC, RECORD_SIZE, VERSION};
+
+impl Record {
+    fn new(cycle: u64, state: &Chip8State, tx: TxHash, gas: u64) -> Self {
+        Record {
+            cycle,
+            pc: state.pc,
+            opcode: state.opcode,
+            v: state.v,
+            i: state.i,
+            sp: state.sp,
+            dt: state.dt,
+            st: state.st,
+            keys: state.keys,
+            tx,
+            gas,
+        }
+    }
+
+    fn to_json(&self) -> Value {
+        json!({
+            "cycle": self.cycle,
+            "pc": self.pc,
+            "opcode": self.opcode,
+            "asm": disassemble(self.opcode),
+            "v": self.v,
+            "i": self.i,
+            "sp": self.sp,
+            "dt": self.dt,
+            "st": self.st,
+            "keys": self.keys,
+            "tx": self.tx,
+            "gas": self.gas,
+        })
+    }
+
+    fn to_bytes(&self) -> [u8; RECORD_SIZE] {
+        let mut out = [0; RECORD_SIZE];
+        let fields: [&[u8]; 11] = [
+            &self.cycle.to_le_bytes(),
+            &self.pc.to_le_bytes(),
+            &self.opcode.to_le_bytes(),
+            &self.v,
+            &self.i.to_le_bytes(),
+            &self.sp.to_le_bytes(),
+            &[self.dt],
+            &[self.st],
+            &self.keys.to_le_bytes(),
+            self.tx.as_slice(),
+            &self.gas.to_le_bytes(),
+        ];
+        let mut at = 0;
+        for field in fields {
+            out[at..at + field.len()].copy_from_slice(field);
+            at += field.len();
+        }
+        out
+    }
+}
+
+enum Format {
+    Jsonl,
+    Binary,
+}
+
+impl Format {
+    fn of(path: &Path) -> Self {
+        match path.extension() {
+            Some(ext) if ext == "bin" => Format::Binary,
+            _ => Format::Jsonl,
+        }
+    }
+}
+
+/// Writes one record per `tick()` to a trace file.
+pub struct Tracer {
+    out: BufWriter<File>,
+    format: Format,
+    cycle: u64,
+}
+
+impl Tracer {
+    pub fn create(path: &Path) -> io::Result<Self> {
+        let format = Format::of(path);
+        let mut out = BufWriter::new(File::create(path)?);
+        if let Format::Binary = format {
+            out.write_all(MAGIC)?;
+            out.write_all(&[VERSION])?;
+        }
+        Ok(Tracer {
+            out,
+            format,
+            cycle: 0,
+        })
+    }
+
+    /// Append the record of the `tick()` that started from `state`.
+    pub fn write(&mut self, state: &Chip8State, tx: TxHash, gas: u64) -> io::Result<Record> {
+        let record = Record::new(self.cycle, state, tx, gas);
+        self.cycle += 1;
+        match self.format {
+            Format::Jsonl => writeln!(self.out, "{}", record.to_json())?,
+            Format::Binary => self.out.write_all(&record.to_bytes())?,
+        }
+        self.out.flush()?;
+        Ok(record)
+    }
+}
@@ -0,0 +1,377 @@
+//! Terminal frontend for playing over SSH: the screen drawn with half-block characters, two
+//! pixel rows per cell, next to the registers, timers and the disassembly around PC.
+//!
+//! Keys map to the keypad as in the SDL window. Most terminals only report key presses, so
+//! unless the terminal reports releases (kitty keyboard protocol) a key stays held until
+//! `HOLD` passes without it repeating.
+
+use std::io;
+use std::path::PathBuf;
+use std::time::{Duration, Instant};
+
+use alloy::network::Network;
+use alloy::primitives::U256;
+use alloy::providers::Provider;
+use alloy::transports::Transport;
+use desktop::client;
+use desktop::display::{Screen, PIXELS};
+use desktop::{SCREEN_HEIGHT, SCREEN_WIDTH};
+use ratatui::crossterm::event::{
+    self, Event, KeyCode, KeyEventKind, KeyModifiers, KeyboardEnhancementFlags,
+    PopKeyboardEnhancementFlags, PushKeyboardEnhancementFlags,
+};
+use ratatui::crossterm::{execute, terminal};
+use ratatui::layout::{Constraint, Layout};
+use ratatui::style::{Style, Stylize};
+use ratatui::text::Line;
+use ratatui::widgets::{Block, Paragraph};
+use ratatui::{DefaultTerminal, Frame};
+
+use crate::callstack;
+use crate::disasm::disassemble;
+use crate::frontend;
+use crate::idle;
+use crate::screenshot;
+use crate::state::Chip8State;
+use crate::storage::read_ram;
+use crate::Solchip8::Solchip8Instance;
+
+/// How long a key counts as held after its last press when releases are not reported.
+const HOLD: Duration = Duration::from_millis(200);
+/// How long to wait for a key when nothing would tick, about a frame at 60Hz.
+const IDLE_WAIT: Duration = Duration::from_millis(16);
+/// Instructions shown before PC in the disassembly panel.
+const DISASM_BEFORE: u16 = 8;
+/// Instructions in the disassembly panel.
+const DISASM_LINES: usize = 24;
+
+/// Everything one frame shows, read from the contract before drawing.
+struct View {
+    state: Chip8State,
+    pixels: [bool; PIXELS],
+    /// RAM from `disasm_start`, `DISASM_LINES` instructions long.
+    code: Vec<u8>,
+    disasm_start: u16,
+    status: String,
+}
+
+/// Run the program loaded in `emu` in the terminal until Esc or Ctrl-C.
+pub async fn play<T, P, N>(
+    emu: &Solchip8Instance<T, P, N>,
+    style: screenshot::Style,
+) -> io::Result<()>
+where
+    T: Transport + Clone,
+    P: Provider<T, N>,
+    N: Network,
+{
+    let mut terminal = ratatui::init();
+    let releases = terminal::supports_keyboard_enhancement().unwrap_or(false);
+    if releases {
+        execute!(
+            io::stdout(),
+            PushKeyboardEnhancementFlags(KeyboardEnhancementFlags::REPORT_EVENT_TYPES)
+        )?;
+    }
+    let result = run(&mut terminal, emu, releases, &style).await;
+    if releases {
+        execute!(io::stdout(), PopKeyboardEnhancementFlags)?;
+    }
+    ratatui::restore();
+    result
+}
+
+async fn run<T, P, N>(
+    terminal: &mut DefaultTerminal,
+    emu: &Solchip8Instance<T, P, N>,
+    releases: bool,
+    style: &screenshot::Style,
+) -> io::Result<()>
+where
+    T: Transport + Clone,
+    P: Provider<T, N>,
+    N: Network,
+{
+    let mut paused = false;
+    // Set once the next tick() would revert, the screen stays up without ticking
+    let mut fault = None;
+    // Keys as last sent with keypress(), and when to release them without release events
+    let mut keys = 0u16;
+    let mut release_at = [None; 16];
+    let mut saved: Option<PathBuf> = None;
+    // Set while the program cannot move without input, ticks are held back
+    let mut idle = None;
+    // PC as last read after a block, and whether that block left it where it was; only then
+    // can the program have gone idle, so only then is it worth asking the contract
+    let mut last_pc = None;
+    let mut stalled = false;
+    // Read again only when a block was mined, nothing else changes the contract
+    let mut screen = Screen::new(emu).await;
+    let mut view: Option<View> = None;
+
+    loop {
+        let mut step = false;
+        let sent = keys;
+        let mut wait = if paused || idle.is_some() {
+            IDLE_WAIT
+        } else {
+            Duration::ZERO
+        };
+        while event::poll(std::mem::take(&mut wait))? {
+            let Event::Key(key) = event::read()? else {
+                continue;
+            };
+            let pressed = key.kind != KeyEventKind::Release;
+            match key.code {
+                KeyCode::Esc => return Ok(()),
+                KeyCode::Char('c') if key.modifiers.contains(KeyModifiers::CONTROL) => {
+                    return Ok(())
+                }
+                KeyCode::F(5) if key.kind == KeyEventKind::Press => paused = !paused,
+                KeyCode::F(11) if key.kind == KeyEventKind::Press => step = true,
+                KeyCode::F(12) if key.kind == KeyEventKind::Press => {
+                    let path = frontend::next_path();
+                    screenshot::capture(emu, &path, style).await?;
+                    saved = Some(path);
+                }
+                code => {
+                    let Some(k) = key2btn(code) else {
+                        continue;
+                    };
+                    if !releases {
+                        release_at[k] = Some(Instant::now() + HOLD);
+                    }
+                    set_key(emu, &mut keys, k, pressed).await?;
+                }
+            }
+        }
+        for (k, at) in release_at.iter_mut().enumerate() {
+            if at.is_some_and(|at| at <= Instant::now()) {
+                *at = None;
+                set_key(emu, &mut keys, k, false).await?;
+            }
+        }
+
+        if (!paused || step) && fault.is_none() {
+            // A key that just went out has to reach a tick, and F11 always ticks
+            idle = if keys != sent || step {
+                None
+            } else if std::mem::take(&mut stalled) {
+                idle::check(emu).await.map_err(io::Error::other)?
+            } else {
+                idle
+            };
+        }
+        if (!paused || step) && fault.is_none() && idle.is_none() {
+            fault = match callstack::check(emu).await.map_err(io::Error::other)? {
+                Some(stack_fault) => Some(stack_fault.to_string()),
+                None => client::transact(emu.tick())
+                    .await
+                    .err()
+                    .map(|e| e.to_string()),
+            };
+        }
+
+        let mined = screen.refresh(emu).await.map_err(io::Error::other)?;
+        let view = match view.as_mut() {
+            Some(view) if !mined => view,
+            _ => {
+                let state = Chip8State::fetch(emu).await.map_err(io::Error::other)?;
+                stalled = last_pc.replace(state.pc) == Some(state.pc);
+                let disasm_start = state.pc.saturating_sub(2 * DISASM_BEFORE);
+                let code = read_ram(emu, disasm_start as usize, 2 * DISASM_LINES)
+                    .await
+                    .map_err(io::Error::other)?;
+                view.insert(View {
+                    state,
+                    pixels: screen.pixels(),
+                    code,
+                    disasm_start,
+                    status: String::new(),
+                })
+            }
+        };
+        let mut status = match &fault {
+            Some(fault) => format!("stopped: {fault}"),
+            None if paused => "paused".to_string(),
+            None => match idle {
+                Some(idle) => idle.to_string(),
+                None => "running".to_string(),
+            },
+        };
+        if let Some(path) = &saved {
+            status += &format!(", saved {}", path.display());
+        }
+        view.status = status;
+        terminal.draw(|frame| render(frame, view))?;
+    }
+}
+
+/// Send a keypress() if `k` is not already in that state.
+async fn set_key<T, P, N>(
+    emu: &Solchip8Instance<T, P, N>,
+    keys: &mut u16,
+    k: usize,
+    pressed: bool,
+) -> io::Result<()>
+where
+    T: Transport + Clone,
+    P: Provider<T, N>,
+    N: Network,
+{
+    if (*keys & (1 << k) != 0) == pressed {
+        return Ok(());
+    }
+    client::transact(emu.keypress(U256::from(k), pressed))
+        .await
+        .map_err(io::Error::other)?;
+    *keys ^= 1 << k;
+    Ok(())
+}
+
+fn render(frame: &mut Frame, view: &View) {
+    let [left, right] = Layout::horizontal([
+        Constraint::Length(SCREEN_WIDTH as u16 + 2),
+        Constraint::Min(24),
+    ])
+    .areas(frame.area());
+    let [screen, registers] = Layout::vertical([
+        Constraint::Length(SCREEN_HEIGHT as u16 / 2 + 2),
+        Constraint::Length(8),
+    ])
+    .areas(left);
+
+    let block = Block::bordered()
+        .title(" Solchip8 ")
+        .title(Line::from(format!(" {} ", view.status)).right_aligned());
+    frame.render_widget(
+        Paragraph::new(half_blocks(&view.pixels)).block(block),
+        screen,
+    );
+
+    let block = Block::bordered()
+        .title(" registers ")
+        .title_bottom(" Esc quit  F5 pause  F11 step  F12 screenshot ");
+    frame.render_widget(
+        Paragraph::new(view.state.to_string()).block(block),
+        registers,
+    );
+
+    let lines: Vec<Line> = view
+        .code
+        .chunks_exact(2)
+        .enumerate()
+        .map(|(n, pair)| {
+            let addr = view.disasm_start + 2 * n as u16;
+            let op = u16::from_be_bytes([pair[0], pair[1]]);
+            let text = format!("{addr:#05x}  {op:04X}  {}", disassemble(op));
+            if addr == view.state.pc {
+                Line::styled(format!("> {text}"), Style::new().bold().reversed())
+            } else {
+                Line::raw(format!("  {text}"))
+            }
+        })
+        .collect();
+    let block = Block::bordered().title(" disassembly ");
+    frame.render_widget(Paragraph::new(lines).block(block), right);
+}
+
+/// The screen as 64×16 cells, each holding a pixel row and the one below it.
+fn half_blocks(pixels: &[bool; PIXELS]) -> Vec<Line<'static>> {
+    pixels
+        .chunks(2 * SCREEN_WIDTH)
+        .map(|rows| {
+            let (top, bottom) = rows.split_at(SCREEN_WIDTH);
+            let cells: String = top
+                .iter()
+                .zip(bottom)
+                .map(|pair| match pair {
+                    (true, true) => '█',
+                    (true, false) => '▀',
+                    (false, true) => '▄',
+                    (false, false) => ' ',
+                })
+                .collect();
+            Line::raw(cells)
+        })
+        .collect()
+}
+
+/// Same layout as `key2btn` in the SDL window, upper or lower case.
+fn key2btn(key: KeyCode) -> Option<usize> {
+    let KeyCode::Char(c) = key else {
+        return None;
+    };
+    match c.to_ascii_lowercase() {
+        '1' => Some(0x1),
+        '2' => Some(0x2),
+        '3' => Some(0x3),
+        '4' => Some(0xC),
+        'q' => Some(0x4),
+        'w' => Some(0x5),
+        'e' => Some(0x6),
+        'r' => Some(0xD),
+        'a' => Some(0x7),
+        's' => Some(0x8),
+        'd' => Some(0x9),
+        'f' => Some(0xE),
+        'z' => Some(0xA),
+        'x' => Some(0x0),
+        'c' => Some(0xB),
+        'v' => Some(0xF),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn text(line: &Line) -> String {
+        line.spans
+            .iter()
+            .map(|span| span.content.as_ref())
+            .collect()
+    }
+
+    #[test]
+    fn half_blocks_pack_two_rows_per_cell() {
+        let mut pixels = [false; PIXELS];
+        // Cell row 0: columns 0-2 cover top only, bottom only and both
+        pixels[0] = true;
+        pixels[SCREEN_WIDTH + 1] = true;
+        pixels[2] = true;
+        pixels[SCREEN_WIDTH + 2] = true;
+        // Bottom-right pixel, the lower half of the last cell
+        pixels[PIXELS - 1] = true;
+
+        let lines = half_blocks(&pixels);
+        assert_eq!(lines.len(), SCREEN_HEIGHT / 2);
+        assert!(lines
+            .iter()
+            .all(|line| text(line).chars().count() == SCREEN_WIDTH));
+        assert_eq!(text(&lines[0]), format!("▀▄█{}", " ".repeat(61)));
+        assert!(lines[1..15].iter().all(|line| text(line).trim().is_empty()));
+        assert_eq!(text(&lines[15]), format!("{}▄", " ".repeat(63)));
+    }
+
+    #[test]
+    fn keys_map_to_the_hex_keypad_in_either_case() {
+        let layout = [
+            ("1234", [0x1, 0x2, 0x3, 0xC]),
+            ("qwer", [0x4, 0x5, 0x6, 0xD]),
+            ("asdf", [0x7, 0x8, 0x9, 0xE]),
+            ("zxcv", [0xA, 0x0, 0xB, 0xF]),
+        ];
+        for (keys, buttons) in layout {
+            for (key, button) in keys.chars().zip(buttons) {
+                assert_eq!(key2btn(KeyCode::Char(key)), Some(button), "{key}");
+                let upper = key.to_ascii_uppercase();
+                assert_eq!(key2btn(KeyCode::Char(upper)), Some(button), "{upper}");
+            }
+        }
+        assert_eq!(key2btn(KeyCode::Char('5')), None);
+        assert_eq!(key2btn(KeyCode::Char('G')), None);
+        assert_eq!(key2btn(KeyCode::Enter), None);
+    }
+}
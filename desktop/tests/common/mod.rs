@@ -0,0 +1,66 @@
+//! The contract bytecode running in revm, so tests need no node.
+
+use alloy::primitives::BlockHash;
+use alloy::sol_types::SolCall;
+use desktop::Solchip8;
+use revm::db::{CacheDB, EmptyDB};
+use revm::primitives::{ExecutionResult, Output, TxKind, U256};
+use revm::{Database, Evm};
+
+pub struct Chip8 {
+    evm: Evm<'static, (), CacheDB<EmptyDB>>,
+    contract: TxKind,
+}
+
+impl Chip8 {
+    /// Deploy a fresh contract.
+    pub fn deploy() -> Self {
+        let evm = Evm::builder()
+            .with_db(CacheDB::new(EmptyDB::default()))
+            .build();
+        let mut chip8 = Chip8 {
+            evm,
+            contract: TxKind::Create,
+        };
+        match chip8.transact(Solchip8::BYTECODE.to_vec()) {
+            Ok(Output::Create(_, Some(address))) => chip8.contract = TxKind::Call(address),
+            other => panic!("deploy failed: {other:?}"),
+        }
+        chip8
+    }
+
+    /// Run the next calls in block `number` at `timestamp`. Returns the hash of its parent.
+    pub fn set_block(&mut self, number: u64, timestamp: u64) -> BlockHash {
+        let block = self.evm.block_mut();
+        block.number = U256::from(number);
+        block.timestamp = U256::from(timestamp);
+        self.evm.db_mut().block_hash(number - 1).unwrap()
+    }
+
+    /// Send `call`, panicking if it reverts.
+    pub fn call<C: SolCall>(&mut self, call: C) -> C::Return {
+        self.try_call(call).unwrap()
+    }
+
+    /// Send `call`, returning the revert reason if it reverts.
+    pub fn try_call<C: SolCall>(&mut self, call: C) -> Result<C::Return, String> {
+        let output = self.transact(call.abi_encode())?;
+        Ok(C::abi_decode_returns(output.data(), true).unwrap())
+    }
+
+    fn transact(&mut self, data: Vec<u8>) -> Result<Output, String> {
+        let tx = self.evm.tx_mut();
+        tx.transact_to = self.contract;
+        tx.data = data.into();
+        match self.evm.transact_commit().unwrap() {
+            ExecutionResult::Success { output, .. } => Ok(output),
+            ExecutionResult::Revert { output, .. } => Err(revert_reason(&output)),
+            ExecutionResult::Halt { reason, .. } => Err(format!("{reason:?}")),
+        }
+    }
+}
+
+/// Message of an `Error(string)` revert, or the raw data.
+fn revert_reason(output: &[u8]) -> String {
+    alloy::sol_types::decode_revert_reason(output).unwrap_or_else(|| format!("{output:?}"))
+}
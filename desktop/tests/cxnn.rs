@@ -0,0 +1,53 @@
+//! Runs the contract bytecode in revm and checks every `CXNN` against `random::predict`.
+
+mod common;
+
+use alloy::primitives::U256;
+use desktop::{random, Solchip8};
+
+use common::Chip8;
+
+#[test]
+fn predictions_match_contract() {
+    let mut chip8 = Chip8::deploy();
+
+    // Every register, the full byte most of the time and a few masks in between
+    let ops: Vec<u16> = (0..64u16)
+        .map(|n| {
+            let nn = [0xFF, 0xFF, 0xFF, 0x0F, 0xFF, 0xA5, 0xFF, 0x80][n as usize % 8];
+            0xC000 | (n % 16) << 8 | nn
+        })
+        .collect();
+    let data = ops.iter().flat_map(|op| op.to_be_bytes()).collect();
+    chip8.call(Solchip8::loadCall { data });
+
+    for (n, &op) in ops.iter().enumerate() {
+        let number = 1_000 + 37 * n as u64;
+        let timestamp = match n % 3 {
+            0 => 1_700_000_000 + n as u64,
+            1 => 0xFFFF_FFFF_FFFF - n as u64,
+            _ => n as u64,
+        };
+        let parent = chip8.set_block(number, timestamp);
+        chip8.call(Solchip8::tickCall {});
+
+        let addr = 0x200 + 2 * n as u16;
+        let (x, expected) = random::predict(op, addr, timestamp, parent).unwrap();
+        let value = chip8
+            .call(Solchip8::getVRegisterCall {
+                index: U256::from(x),
+            })
+            ._0;
+        assert_eq!(
+            value, expected,
+            "{op:04X} at {addr:#05x} in block {number} at {timestamp}"
+        );
+    }
+}
+
+#[test]
+fn other_opcodes_are_not_predicted() {
+    for op in [0x0000, 0x00E0, 0x1200, 0x6A12, 0xB123, 0xD015, 0xF00A] {
+        assert_eq!(random::predict(op, 0x200, 0, Default::default()), None);
+    }
+}
@@ -0,0 +1,183 @@
+//! Golden frames: every game in `c8games` runs for `TICKS` ticks with the same scripted input
+//! as `bench`, and the final screen must match `tests/golden/<ROM>.pbm`.
+//!
+//! Every call is made in its own block with the timestamp one second after the last, so
+//! `CXNN` draws the same numbers every run. Set `UPDATE_GOLDEN=1` to write the current frames
+//! as the new goldens after an intended change.
+
+mod common;
+
+use std::collections::BTreeSet;
+use std::fmt::Write;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use alloy::primitives::U256;
+use desktop::display::{self, PIXELS};
+use desktop::input::Script;
+use desktop::{Solchip8, SCREEN_WIDTH};
+
+use common::Chip8;
+
+/// Ticks a game runs before its frame is compared, unless it sets its own.
+const TICKS: u64 = 600;
+/// Timestamp of the first block.
+const GENESIS: u64 = 1_700_000_000;
+
+/// Screen after `ticks` ticks of the ROM at `path` with the input of `Script::cycle_keys`, or
+/// the revert that stopped it.
+fn run(path: &Path, ticks: u64) -> Result<[bool; PIXELS], String> {
+    let mut chip8 = Chip8::deploy();
+    let mut block = 0;
+    let mut next_block = |chip8: &mut Chip8| {
+        block += 1;
+        chip8.set_block(block, GENESIS + block);
+    };
+
+    next_block(&mut chip8);
+    let data = fs::read(path).unwrap();
+    chip8.call(Solchip8::loadCall { data });
+
+    let input = Script::cycle_keys(ticks);
+    for tick in 0..ticks {
+        for (key, pressed) in input.at(tick) {
+            next_block(&mut chip8);
+            chip8.call(Solchip8::keypressCall {
+                idx: U256::from(key),
+                pressed,
+            });
+        }
+        next_block(&mut chip8);
+        chip8
+            .try_call(Solchip8::tickCall {})
+            .map_err(|e| format!("tick {tick} reverted: {e}"))?;
+    }
+    Ok(display::decode(&chip8.call(Solchip8::getDisplayCall {})._0))
+}
+
+fn parse_pbm(source: &str) -> [bool; PIXELS] {
+    let mut pixels = [false; PIXELS];
+    let bits = source
+        .lines()
+        .skip(2)
+        .flat_map(|line| line.chars())
+        .filter(|c| !c.is_whitespace());
+    for (pixel, bit) in pixels.iter_mut().zip(bits) {
+        *pixel = bit == '1';
+    }
+    pixels
+}
+
+/// The grid with `#` lit and `.` unlit in both frames, `+` lit only in `actual` and `-` lit
+/// only in `expected`.
+fn ascii_diff(expected: &[bool; PIXELS], actual: &[bool; PIXELS]) -> String {
+    let mut out = String::from("    ");
+    out.extend((0..SCREEN_WIDTH).map(|x| if x % 10 == 0 { '|' } else { ' ' }));
+    out.push('\n');
+    for (y, (want, got)) in expected
+        .chunks(SCREEN_WIDTH)
+        .zip(actual.chunks(SCREEN_WIDTH))
+        .enumerate()
+    {
+        write!(out, "{y:2}  ").unwrap();
+        out.extend(want.iter().zip(got).map(|pair| match pair {
+            (true, true) => '#',
+            (false, false) => '.',
+            (false, true) => '+',
+            (true, false) => '-',
+        }));
+        out.push('\n');
+    }
+    out
+}
+
+fn games_dir() -> PathBuf {
+    Path::new(env!("CARGO_MANIFEST_DIR")).join("../c8games")
+}
+
+fn golden_dir() -> PathBuf {
+    Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/golden")
+}
+
+fn check(rom: &str, ticks: u64) {
+    let path = games_dir().join(rom);
+    let golden = golden_dir().join(rom).with_extension("pbm");
+
+    let actual = run(&path, ticks).unwrap_or_else(|e| panic!("{rom}: {e}"));
+    if std::env::var_os("UPDATE_GOLDEN").is_some() {
+        fs::create_dir_all(golden.parent().unwrap()).unwrap();
+        fs::write(&golden, display::to_pbm(&actual)).unwrap();
+        return;
+    }
+    let source = fs::read_to_string(&golden).unwrap_or_else(|e| {
+        panic!(
+            "{}: {e}, run with UPDATE_GOLDEN=1 to create it",
+            golden.display()
+        )
+    });
+    let expected = parse_pbm(&source);
+    let differing = expected.iter().zip(&actual).filter(|(a, b)| a != b).count();
+    assert!(
+        differing == 0,
+        "{rom}: {differing} pixels differ from {} after {ticks} ticks \
+         (+ lit only now, - lit only in the golden)\n{}",
+        golden.display(),
+        ascii_diff(&expected, &actual)
+    );
+}
+
+#[test]
+fn every_game_has_a_golden() {
+    let names = |dir: PathBuf| -> BTreeSet<String> {
+        fs::read_dir(dir)
+            .unwrap()
+            .map(|entry| entry.unwrap().path())
+            .filter_map(|path| Some(path.file_stem()?.to_str()?.to_string()))
+            .collect()
+    };
+    assert_eq!(
+        names(games_dir()),
+        names(golden_dir()),
+        "add new games to `golden!` and run with UPDATE_GOLDEN=1"
+    );
+}
+
+macro_rules! golden {
+    (@ticks) => { TICKS };
+    (@ticks $ticks:literal) => { $ticks };
+    ($($name:ident => $rom:literal $(for $ticks:literal ticks)?,)*) => {
+        $(
+            #[test]
+            fn $name() {
+                check($rom, golden!(@ticks $($ticks)?));
+            }
+        )*
+    };
+}
+
+golden! {
+    puzzle15 => "15PUZZLE",
+    // Draws its maze slowly, the screen is still blank after the default 600 ticks
+    blinky => "BLINKY" for 5000 ticks,
+    blitz => "BLITZ",
+    brix => "BRIX",
+    connect4 => "CONNECT4",
+    guess => "GUESS",
+    hidden => "HIDDEN",
+    invaders => "INVADERS",
+    kaleid => "KALEID",
+    maze => "MAZE",
+    merlin => "MERLIN",
+    missile => "MISSILE",
+    pong => "PONG",
+    pong2 => "PONG2",
+    puzzle => "PUZZLE",
+    syzygy => "SYZYGY",
+    tank => "TANK",
+    tetris => "TETRIS",
+    tictac => "TICTAC",
+    ufo => "UFO",
+    vbrix => "VBRIX",
+    vers => "VERS",
+    wipeoff => "WIPEOFF",
+}